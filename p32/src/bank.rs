@@ -1,26 +1,162 @@
+pub mod auth;
+pub mod batch;
+pub mod books;
+pub mod clearing;
+pub mod concurrent;
+pub mod fees;
+pub mod interest;
+pub mod ledger;
+pub mod limits;
+pub mod loans;
+pub mod merge;
+pub mod money;
+pub mod persist;
+pub mod query;
+pub mod risk;
+pub mod simulate;
+pub mod split;
+pub mod standing;
+pub mod statement;
+pub mod status;
+pub mod verify;
+
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
 
+pub use auth::{Credentials, Operation, Role, SecuredBank};
+pub use batch::{BatchError, BatchMode, BatchReport, Transfer};
+pub use books::{Account, InternalAccount, Posting, TrialBalance, TrialBalanceLine};
+pub use clearing::{ClearingError, ClearingHouse, NetPosition, Payment, PaymentId, Settlement};
+pub use concurrent::ConcurrentBank;
+pub use fees::{Fee, FeeFailure, FeeRule, FeeTier, FeeTrigger};
+pub use interest::{CompoundingPeriod, DayCount, InterestSchedule};
+pub use ledger::{EntryKind, Ledger, LedgerEntry, ReplayError};
+pub use limits::{DailyUsage, Limit, TransferLimits};
+pub use loans::{Installment, Loan, LoanError, LoanId, LoanTerms, PaymentFrequency};
+pub use merge::{MergeConflict, MergePolicy, MergeReport};
+pub use money::{Money, Rounding};
+pub use persist::PersistError;
+pub use query::{SortKey, UserQuery};
+pub use risk::{Concentration, Exposure, RiskReport};
+pub use simulate::{Rng, Sample, SeriesStats, SimConfig, SimReport};
+pub use split::SplitError;
+pub use standing::{ClockReport, OrderError, OrderId, OrderRun, StandingOrder};
+pub use statement::{Statement, StatementLine};
+pub use status::AccountStatus;
+pub use verify::Violation;
+
+/// Stable identifier of an account within one bank
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AccountId(pub u64);
+
+impl fmt::Display for AccountId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct User {
-    pub name: String,
-    pub credit_line: u64,
-    pub balance: i64, // positive = debit, negative = credit
+    name: String,
+    credit_line: Money,
+    balance: Money,      // positive = debit, negative = credit
+    id: AccountId,       // assigned by the bank in add_user
+    interest_carry: i64, // unposted interest, in 1/CARRY_SCALE minor units
+    status: AccountStatus,
+    limits: TransferLimits,
+    usage: DailyUsage, // counted only while a daily limit is set
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Bank {
     pub name: String,
-    pub users: Vec<User>,
+    users: Vec<User>,
     pub credit_interest: u64, // in basis points (0.01%)
     pub debit_interest: u64,  // in basis points (0.01%)
+    pub interest_schedule: Option<InterestSchedule>,
+    pub fees: Vec<FeeRule>,
+    fee_revenue: Money, // fees collected, owned by the bank
+    ledger: Ledger,
+    next_id: u64,
+    by_id: HashMap<AccountId, usize>,
+    by_name: HashMap<String, usize>, // keyed by normalize_name
+    today: u64,                      // logical clock, in days
+    orders: Vec<StandingOrder>,
+    next_order_id: u64,
+    loans: Vec<Loan>,
+    next_loan_id: u64,
 }
 
+#[derive(Debug)]
+pub enum AccountError {
+    DuplicateUser(String),
+    UserNotFound(String),
+    NegativeCreditLine(Money),
+    NegativeLimit(Money),
+    BeyondCreditLine(String),
+    AccountClosed(String),
+    /// Closing requires a zero balance
+    BalanceNotZero(String),
+    /// Closing requires every loan of the user to be repaid
+    LoanOutstanding(String),
+    InvalidTransition {
+        name: String,
+        from: AccountStatus,
+        to: AccountStatus,
+    },
+}
+
+impl fmt::Display for AccountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccountError::DuplicateUser(name) => write!(f, "User {} already exists", name),
+            AccountError::UserNotFound(name) => write!(f, "User {} not found", name),
+            AccountError::NegativeCreditLine(amount) => {
+                write!(f, "Credit line cannot be negative: {}", amount)
+            }
+            AccountError::NegativeLimit(amount) => {
+                write!(f, "Transfer limit cannot be negative: {}", amount)
+            }
+            AccountError::BeyondCreditLine(name) => {
+                write!(f, "Balance of {} would exceed their credit line", name)
+            }
+            AccountError::AccountClosed(name) => write!(f, "Account of {} is closed", name),
+            AccountError::BalanceNotZero(name) => {
+                write!(f, "Account of {} still has a balance", name)
+            }
+            AccountError::LoanOutstanding(name) => {
+                write!(f, "User {} still has a loan outstanding", name)
+            }
+            AccountError::InvalidTransition { name, from, to } => {
+                write!(f, "Account of {} cannot go from {} to {}", name, from, to)
+            }
+        }
+    }
+}
+
+impl Error for AccountError {}
+
 #[derive(Debug)]
 pub enum TransferError {
     UserNotFound(String),
     InsufficientFunds(String),
     CreditLimitExceeded(String),
+    ZeroAmount,
+    NegativeAmount(Money),
+    SelfTransfer(String),
+    ArithmeticOverflow(String),
+    AccountFrozen(String),
+    AccountClosed(String),
+    /// Caller's credentials are wrong or their role does not permit the call
+    Unauthorized(String),
+    /// No caller with this login exists, e.g. when revoking one
+    UnknownLogin(String),
+    /// Transfer would break one of the sender's [`TransferLimits`]
+    LimitExceeded {
+        name: String,
+        limit: Limit,
+    },
 }
 
 impl fmt::Display for TransferError {
@@ -33,18 +169,109 @@ impl fmt::Display for TransferError {
             TransferError::CreditLimitExceeded(name) => {
                 write!(f, "User {} would exceed credit line", name)
             }
+            TransferError::ZeroAmount => write!(f, "Transfer amount must be positive"),
+            TransferError::NegativeAmount(amount) => {
+                write!(f, "Transfer amount {} is negative", amount)
+            }
+            TransferError::SelfTransfer(name) => {
+                write!(f, "User {} cannot transfer to themselves", name)
+            }
+            TransferError::ArithmeticOverflow(name) => {
+                write!(f, "Balance of user {} would overflow", name)
+            }
+            TransferError::AccountFrozen(name) => write!(f, "Account of {} is frozen", name),
+            TransferError::AccountClosed(name) => write!(f, "Account of {} is closed", name),
+            TransferError::Unauthorized(login) => {
+                write!(f, "Caller {} is not authorized for this operation", login)
+            }
+            TransferError::UnknownLogin(login) => write!(f, "Login {} not found", login),
+            TransferError::LimitExceeded { name, limit } => {
+                write!(
+                    f,
+                    "User {} would exceed their transfer limit: {}",
+                    name, limit
+                )
+            }
         }
     }
 }
 
 impl Error for TransferError {}
 
+impl TransferError {
+    /// Short stable name of the variant, e.g. `insufficient-funds`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TransferError::UserNotFound(_) => "user-not-found",
+            TransferError::InsufficientFunds(_) => "insufficient-funds",
+            TransferError::CreditLimitExceeded(_) => "credit-limit-exceeded",
+            TransferError::ZeroAmount => "zero-amount",
+            TransferError::NegativeAmount(_) => "negative-amount",
+            TransferError::SelfTransfer(_) => "self-transfer",
+            TransferError::ArithmeticOverflow(_) => "arithmetic-overflow",
+            TransferError::AccountFrozen(_) => "account-frozen",
+            TransferError::AccountClosed(_) => "account-closed",
+            TransferError::Unauthorized(_) => "unauthorized",
+            TransferError::UnknownLogin(_) => "unknown-login",
+            TransferError::LimitExceeded { .. } => "limit-exceeded",
+        }
+    }
+}
+
 impl User {
-    pub fn new(name: String, credit_line: u64, balance: i64) -> Self {
+    pub fn new(name: String, credit_line: Money, balance: Money) -> Self {
         Self {
             name,
             credit_line,
             balance,
+            id: AccountId::default(),
+            interest_carry: 0,
+            status: AccountStatus::Active,
+            limits: TransferLimits::default(),
+            usage: DailyUsage::default(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn credit_line(&self) -> Money {
+        self.credit_line
+    }
+
+    pub fn balance(&self) -> Money {
+        self.balance
+    }
+
+    pub fn id(&self) -> AccountId {
+        self.id
+    }
+
+    pub fn status(&self) -> AccountStatus {
+        self.status
+    }
+
+    /// Balance plus credit line: the most this user can send while active
+    pub fn available_funds(&self) -> Money {
+        self.balance.saturating_add(self.credit_line)
+    }
+
+    /// Overdraft as a share of the credit line in basis points; above 10000
+    /// if overdrawn past the line, `None` without a credit line
+    pub fn utilisation_bps(&self) -> Option<u64> {
+        risk::utilisation((-self.balance).max(Money::ZERO), self.credit_line)
+    }
+
+    fn overflow(&self) -> TransferError {
+        TransferError::ArithmeticOverflow(self.name.clone())
+    }
+
+    /// Fails unless the account may receive money
+    fn check_open(&self) -> Result<(), TransferError> {
+        match self.status {
+            AccountStatus::Closed => Err(TransferError::AccountClosed(self.name.clone())),
+            _ => Ok(()),
         }
     }
 }
@@ -56,101 +283,333 @@ impl Bank {
             users: Vec::new(),
             credit_interest,
             debit_interest,
+            interest_schedule: None,
+            fees: Vec::new(),
+            fee_revenue: Money::ZERO,
+            ledger: Ledger::default(),
+            next_id: 1,
+            by_id: HashMap::new(),
+            by_name: HashMap::new(),
+            today: 0,
+            orders: Vec::new(),
+            next_order_id: 1,
+            loans: Vec::new(),
+            next_loan_id: 1,
+        }
+    }
+
+    /// Adds a user and returns its new account id.
+    ///
+    /// Names are unique ignoring case and surrounding whitespace. The credit
+    /// line must not be negative and must cover any opening overdraft.
+    pub fn add_user(&mut self, user: User) -> Result<AccountId, AccountError> {
+        if self.position(&user.name).is_some() {
+            return Err(AccountError::DuplicateUser(user.name));
+        }
+        check_credit_line(&user, user.credit_line)?;
+
+        let kind = EntryKind::Open {
+            credit_line: user.credit_line,
+        };
+        let balance = user.balance;
+        let idx = self.insert_user(user);
+        self.record_deposit(kind, idx, balance);
+        Ok(self.users[idx].id)
+    }
+
+    /// Changes a user's credit line, recording the change in the ledger.
+    ///
+    /// Fails if the account is closed or the new line is negative or no
+    /// longer covers the user's current overdraft.
+    pub fn set_credit_line(&mut self, name: &str, credit_line: Money) -> Result<(), AccountError> {
+        let idx = self
+            .position(name)
+            .ok_or_else(|| AccountError::UserNotFound(name.to_string()))?;
+        let user = &self.users[idx];
+        if user.status == AccountStatus::Closed {
+            return Err(AccountError::AccountClosed(user.name.clone()));
         }
+        check_credit_line(user, credit_line)?;
+
+        self.users[idx].credit_line = credit_line;
+        let kind = EntryKind::CreditLine { credit_line };
+        self.record(kind, None, Some(idx), Money::ZERO);
+        Ok(())
+    }
+
+    pub fn users(&self) -> &[User] {
+        &self.users
+    }
+
+    /// Looks up a user by name, ignoring case and surrounding whitespace
+    pub fn user(&self, name: &str) -> Option<&User> {
+        self.position(name).map(|idx| &self.users[idx])
+    }
+
+    pub fn user_by_id(&self, id: AccountId) -> Option<&User> {
+        self.by_id.get(&id).map(|&idx| &self.users[idx])
     }
 
-    pub fn add_user(&mut self, user: User) {
+    /// Assigns the next account id and indexes the user, without recording it
+    fn insert_user(&mut self, mut user: User) -> usize {
+        let idx = self.users.len();
+        user.id = AccountId(self.next_id);
+        self.next_id += 1;
+
+        self.by_id.insert(user.id, idx);
+        self.by_name.insert(normalize_name(&user.name), idx);
         self.users.push(user);
+        idx
     }
 
-    pub fn calc_balance(&self) -> (u64, u64) {
-        let mut liabilities = 0;
-        let mut assets = 0;
+    /// Rebuilds both user indexes after users were removed
+    fn reindex(&mut self) {
+        self.by_id.clear();
+        self.by_name.clear();
+        for (idx, user) in self.users.iter().enumerate() {
+            self.by_id.insert(user.id, idx);
+            self.by_name.insert(normalize_name(&user.name), idx);
+        }
+    }
 
-        for user in &self.users {
-            if user.balance > 0 {
-                liabilities += user.balance as u64;
-            } else {
-                assets += user.balance.unsigned_abs();
-            }
+    fn position(&self, name: &str) -> Option<usize> {
+        self.by_name.get(&normalize_name(name)).copied()
+    }
+
+    /// Every balance change made to this bank, oldest first
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    fn record(&mut self, kind: EntryKind, from: Option<usize>, to: Option<usize>, amount: Money) {
+        let from = from.map(|idx| &self.users[idx]);
+        let to = to.map(|idx| &self.users[idx]);
+        self.ledger.push(kind, from, to, amount);
+    }
+
+    /// Records a signed amount moving between the bank and one user
+    fn record_deposit(&mut self, kind: EntryKind, idx: usize, amount: Money) {
+        if amount.is_negative() {
+            self.record(kind, Some(idx), None, amount.abs());
+        } else {
+            self.record(kind, None, Some(idx), amount);
         }
+    }
 
-        (liabilities, assets)
+    /// Total positive balances and fee revenue (liabilities) and total
+    /// overdrafts and loans outstanding (assets).
+    ///
+    /// Each total saturates at [`Money::MAX`].
+    pub fn calc_balance(&self) -> (Money, Money) {
+        let (liabilities, assets) = balance_totals(&self.users);
+        (
+            liabilities.saturating_add(self.fee_revenue),
+            assets.saturating_add(self.loans_outstanding()),
+        )
     }
 
     pub fn transfer_funds(
         &mut self,
         from_name: &str,
         to_name: &str,
-        amount: u64,
+        amount: Money,
     ) -> Result<(), TransferError> {
-        let amount_i64 = amount as i64;
-
         // Find user indices first
         let from_idx = self
-            .users
-            .iter()
-            .position(|u| u.name == from_name)
+            .position(from_name)
             .ok_or(TransferError::UserNotFound(from_name.to_string()))?;
 
         let to_idx = self
-            .users
-            .iter()
-            .position(|u| u.name == to_name)
+            .position(to_name)
             .ok_or(TransferError::UserNotFound(to_name.to_string()))?;
 
-        // Check if transfer is possible
-        if (self.users[from_idx].balance - amount_i64).unsigned_abs()
-            > self.users[from_idx].credit_line
-        {
-            return Err(TransferError::CreditLimitExceeded(from_name.to_string()));
+        if from_idx == to_idx {
+            return Err(TransferError::SelfTransfer(from_name.to_string()));
         }
+        let (from_balance, fees) = self.check_outgoing(from_idx, amount)?;
+        let to = &self.users[to_idx];
+        to.check_open()?;
+        let to_balance = to.balance.checked_add(amount).ok_or(to.overflow())?;
 
         // Execute transfer
-        self.users[from_idx].balance -= amount_i64;
-        self.users[to_idx].balance += amount_i64;
-
+        self.users[from_idx].balance = from_balance;
+        self.users[to_idx].balance = to_balance;
+        self.record(EntryKind::Transfer, Some(from_idx), Some(to_idx), amount);
+        self.settle_outgoing(from_idx, amount, fees);
         Ok(())
     }
 
-    pub fn accrue_interest(&mut self) {
-        use std::cmp::Ordering;
-
-        for user in &mut self.users {
-            match user.balance.cmp(&0) {
-                Ordering::Greater => {
-                    // Debit interest with rounding
-                    let interest = (user.balance * self.debit_interest as i64 + 5000) / 10000;
-                    user.balance += interest;
-                }
-                Ordering::Less => {
-                    // Credit interest with rounding
-                    let abs_balance = (-user.balance) as u64;
-                    let interest = ((abs_balance * self.credit_interest + 5000) / 10000) as i64;
-                    user.balance -= interest;
-                }
-                Ordering::Equal => {} // No interest on zero balance
-            }
+    /// Checks that user `idx` can send `amount` under the same rules as a
+    /// transfer: funds, fees and transfer limits.
+    ///
+    /// Returns their new balance, excluding the fees, and the fees owed.
+    fn check_outgoing(
+        &self,
+        idx: usize,
+        amount: Money,
+    ) -> Result<(Money, Vec<(FeeTrigger, Money)>), TransferError> {
+        let from = &self.users[idx];
+        let fees = fees::transfer_fees(&self.fees, from, amount)?;
+        let fee = fees::fee_total(&fees, from, self.fee_revenue)?;
+        let balance = check_withdrawal(from, amount, fee)?;
+        limits::check(from, amount, self.today)?;
+        Ok((balance, fees))
+    }
+
+    /// Counts `amount` sent by user `idx` against their limits and charges
+    /// the fees returned by [`Bank::check_outgoing`]
+    fn settle_outgoing(&mut self, idx: usize, amount: Money, fees: Vec<(FeeTrigger, Money)>) {
+        let user = &mut self.users[idx];
+        limits::record(user, amount, self.today);
+        for (trigger, fee) in fees {
+            fees::charge(user, &mut self.ledger, trigger, fee);
+            self.fee_revenue = self.fee_revenue.saturating_add(fee);
         }
     }
 
-    /// Merges another bank into this one, consuming the other bank
-    pub fn merge_bank(&mut self, other: Bank) {
-        for other_user in other.users {
-            // Try to find existing user with same name
-            if let Some(existing_user) = self.users.iter_mut().find(|u| u.name == other_user.name) {
-                // Update balance if user exists in both banks
-                existing_user.balance += other_user.balance;
+    /// Applies one interest step to every open account.
+    ///
+    /// Without an [`InterestSchedule`] the full rates are applied once,
+    /// rounding half up. With one, the rates are annual and one compounding
+    /// period is accrued. All new balances are computed before any is
+    /// applied, so an overflow leaves the bank unchanged.
+    pub fn accrue_interest(&mut self) -> Result<(), TransferError> {
+        if let Some(schedule) = self.interest_schedule {
+            return self.accrue_scheduled_interest(schedule);
+        }
 
-                // Sum credit lines (since merging banks combines their capacity)
-                existing_user.credit_line += other_user.credit_line;
+        let mut updates = Vec::with_capacity(self.users.len());
+        for user in &self.users {
+            if user.status == AccountStatus::Closed {
+                updates.push((Money::ZERO, user.balance));
+                continue;
+            }
+            // Debit interest on positive balances, credit interest on negative
+            let rate = if user.balance.is_positive() {
+                self.debit_interest
             } else {
-                // Add new user if they don't exist in this bank
-                self.users.push(other_user);
+                self.credit_interest
+            };
+            let interest = user
+                .balance
+                .checked_mul_bps(rate, Rounding::HalfUp)
+                .ok_or(user.overflow())?;
+            let balance = user.balance.checked_add(interest).ok_or(user.overflow())?;
+            updates.push((interest, balance));
+        }
+
+        for (idx, (interest, balance)) in updates.into_iter().enumerate() {
+            if !interest.is_zero() {
+                self.users[idx].balance = balance;
+                let carry = self.users[idx].interest_carry;
+                self.record_deposit(EntryKind::Interest { carry }, idx, interest);
             }
         }
+        Ok(())
+    }
+}
+
+/// Liabilities and assets of `users`, as in [`Bank::calc_balance`]
+fn balance_totals<'a>(users: impl IntoIterator<Item = &'a User>) -> (Money, Money) {
+    let mut liabilities = Money::ZERO;
+    let mut assets = Money::ZERO;
+
+    for user in users {
+        if user.balance.is_positive() {
+            liabilities = liabilities.saturating_add(user.balance);
+        } else {
+            assets = assets.saturating_add(user.balance.abs());
+        }
+    }
+
+    (liabilities, assets)
+}
+
+/// Validates a transfer between two distinct users and returns their new balances.
+///
+/// `fee` is charged to the sender on top of `amount` and must fit within
+/// their funds too; it is not included in the returned balances.
+fn check_transfer(
+    from: &User,
+    to: &User,
+    amount: Money,
+    fee: Money,
+) -> Result<(Money, Money), TransferError> {
+    let from_balance = check_withdrawal(from, amount, fee)?;
+    to.check_open()?;
+    let to_balance = to.balance.checked_add(amount).ok_or(to.overflow())?;
+    Ok((from_balance, to_balance))
+}
+
+/// Validates `amount` plus `fee` leaving `from` and returns their new
+/// balance, excluding the fee
+fn check_withdrawal(from: &User, amount: Money, fee: Money) -> Result<Money, TransferError> {
+    from.check_open()?;
+    if from.status == AccountStatus::Frozen {
+        return Err(TransferError::AccountFrozen(from.name.clone()));
+    }
+    if amount.is_zero() {
+        return Err(TransferError::ZeroAmount);
+    }
+    if amount.is_negative() {
+        return Err(TransferError::NegativeAmount(amount));
+    }
+
+    // Check if transfer is possible: users without a credit line can only
+    // spend their balance, others may overdraw down to -credit_line
+    let total = amount.checked_add(fee).ok_or(from.overflow())?;
+    if total > from.available_funds() {
+        return Err(if from.credit_line.is_zero() {
+            TransferError::InsufficientFunds(from.name.clone())
+        } else {
+            TransferError::CreditLimitExceeded(from.name.clone())
+        });
+    }
+
+    let from_balance = from.balance.checked_sub(amount).ok_or(from.overflow())?;
+    from_balance.checked_sub(fee).ok_or(from.overflow())?;
+    Ok(from_balance)
+}
+
+fn check_credit_line(user: &User, credit_line: Money) -> Result<(), AccountError> {
+    if credit_line.is_negative() {
+        return Err(AccountError::NegativeCreditLine(credit_line));
+    }
+    if user.balance < -credit_line {
+        return Err(AccountError::BeyondCreditLine(user.name.clone()));
+    }
+    Ok(())
+}
+
+/// Key used to match user names: case-insensitive, surrounding whitespace ignored
+fn normalize_name(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// Fixtures shared by the unit tests of the bank modules
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::{Bank, User};
+
+    /// Bank with `rates` (credit, debit) and `users` given as name, credit
+    /// line and balance in minor units
+    pub(crate) fn bank(name: &str, rates: (u64, u64), users: &[(&str, i64, i64)]) -> Bank {
+        let mut bank = Bank::new(name.to_string(), rates.0, rates.1);
+        for &(name, credit_line, balance) in users {
+            let user = User::new(name.to_string(), credit_line.into(), balance.into());
+            bank.add_user(user).unwrap();
+        }
+        bank
+    }
 
-        // other bank is now consumed/destroyed
+    /// Checks that replaying the ledger of `bank` onto an empty bank with the
+    /// same name, rates, schedule and fee rules rebuilds it exactly
+    pub(crate) fn assert_replays(bank: &Bank) {
+        let mut rebuilt = Bank::new(bank.name.clone(), bank.credit_interest, bank.debit_interest);
+        rebuilt.interest_schedule = bank.interest_schedule;
+        rebuilt.fees = bank.fees.clone();
+        rebuilt.replay(bank.ledger()).unwrap();
+        assert_eq!(&rebuilt, bank);
     }
 }
 
@@ -160,80 +619,224 @@ mod tests {
 
     #[test]
     fn test_user_creation() {
-        let user = User::new("Alice".to_string(), 5000, 1000);
+        let user = User::new("Alice".to_string(), 5000.into(), 1000.into());
         assert_eq!(user.name, "Alice");
-        assert_eq!(user.credit_line, 5000);
-        assert_eq!(user.balance, 1000);
+        assert_eq!(user.credit_line, Money::from_minor(5000));
+        assert_eq!(user.balance, Money::from_minor(1000));
     }
 
     #[test]
     fn test_bank_operations() {
         let mut bank = Bank::new("Rust Bank".to_string(), 500, 1000);
-        bank.add_user(User::new("Bob".to_string(), 3000, -200));
+        bank.add_user(User::new("Bob".to_string(), 3000.into(), (-200).into()))
+            .unwrap();
         assert_eq!(bank.users.len(), 1);
     }
 
+    #[test]
+    fn test_add_user_rejects_duplicates() {
+        let mut bank = Bank::new("Rust Bank".to_string(), 500, 1000);
+        let alice = bank
+            .add_user(User::new("Alice".to_string(), 0.into(), 0.into()))
+            .unwrap();
+        let bob = bank
+            .add_user(User::new("Bob".to_string(), 0.into(), 0.into()))
+            .unwrap();
+        assert_ne!(alice, bob);
+
+        assert!(matches!(
+            bank.add_user(User::new(" alice ".to_string(), 0.into(), 0.into())),
+            Err(AccountError::DuplicateUser(_))
+        ));
+        assert_eq!(bank.users().len(), 2);
+    }
+
+    #[test]
+    fn test_user_lookup() {
+        let mut bank = Bank::new("Rust Bank".to_string(), 500, 1000);
+        bank.add_user(User::new("Alice".to_string(), 0.into(), 100.into()))
+            .unwrap();
+        let bob = bank
+            .add_user(User::new("Bob".to_string(), 0.into(), 0.into()))
+            .unwrap();
+
+        assert_eq!(bank.user_by_id(bob).unwrap().name, "Bob");
+        assert_eq!(bank.user("BOB").unwrap().id(), bob);
+        assert!(bank.user("Carol").is_none());
+
+        bank.transfer_funds("alice", "bob", 100.into()).unwrap();
+        assert_eq!(
+            bank.user_by_id(bob).unwrap().balance,
+            Money::from_minor(100)
+        );
+    }
+
     #[test]
     fn test_calc_balance() {
         let mut bank = Bank::new("Test Bank".to_string(), 500, 1000);
-        bank.add_user(User::new("Alice".to_string(), 5000, 2000));
-        bank.add_user(User::new("Bob".to_string(), 3000, -1000));
+        bank.add_user(User::new("Alice".to_string(), 5000.into(), 2000.into()))
+            .unwrap();
+        bank.add_user(User::new("Bob".to_string(), 3000.into(), (-1000).into()))
+            .unwrap();
 
         let (liabilities, assets) = bank.calc_balance();
-        assert_eq!(liabilities, 2000);
-        assert_eq!(assets, 1000);
+        assert_eq!(liabilities, Money::from_minor(2000));
+        assert_eq!(assets, Money::from_minor(1000));
     }
 
     #[test]
     fn test_transfer_funds() {
         let mut bank = Bank::new("Test Bank".to_string(), 0, 0);
-        bank.add_user(User::new("Alice".to_string(), 5000, 2000));
-        bank.add_user(User::new("Bob".to_string(), 3000, -500));
+        bank.add_user(User::new("Alice".to_string(), 5000.into(), 2000.into()))
+            .unwrap();
+        bank.add_user(User::new("Bob".to_string(), 3000.into(), (-500).into()))
+            .unwrap();
+
+        bank.transfer_funds("Alice", "Bob", 500.into()).unwrap();
+        assert_eq!(bank.users[0].balance, Money::from_minor(1500));
+        assert_eq!(bank.users[1].balance, Money::from_minor(0));
+
+        assert!(bank.transfer_funds("Alice", "Bob", 7500.into()).is_err());
+    }
+
+    #[test]
+    fn test_transfer_from_large_positive_balance() {
+        let mut bank = Bank::new("Test Bank".to_string(), 0, 0);
+        bank.add_user(User::new("Alice".to_string(), 0.into(), 10000.into()))
+            .unwrap();
+        bank.add_user(User::new("Bob".to_string(), 0.into(), 0.into()))
+            .unwrap();
+
+        bank.transfer_funds("Alice", "Bob", 1.into()).unwrap();
+        bank.transfer_funds("Alice", "Bob", 9999.into()).unwrap();
+        assert_eq!(bank.users[0].balance, Money::from_minor(0));
+        assert_eq!(bank.users[1].balance, Money::from_minor(10000));
+    }
+
+    #[test]
+    fn test_transfer_limits() {
+        let mut bank = Bank::new("Test Bank".to_string(), 0, 0);
+        bank.add_user(User::new("Alice".to_string(), 0.into(), 100.into()))
+            .unwrap();
+        bank.add_user(User::new("Bob".to_string(), 500.into(), 100.into()))
+            .unwrap();
+
+        assert!(matches!(
+            bank.transfer_funds("Alice", "Bob", 101.into()),
+            Err(TransferError::InsufficientFunds(_))
+        ));
+
+        // Bob may overdraw down to exactly -500
+        bank.transfer_funds("Bob", "Alice", 600.into()).unwrap();
+        assert_eq!(bank.users[1].balance, Money::from_minor(-500));
+        assert!(matches!(
+            bank.transfer_funds("Bob", "Alice", 1.into()),
+            Err(TransferError::CreditLimitExceeded(_))
+        ));
+    }
+
+    #[test]
+    fn test_transfer_rejects_zero_and_self() {
+        let mut bank = Bank::new("Test Bank".to_string(), 0, 0);
+        bank.add_user(User::new("Alice".to_string(), 0.into(), 100.into()))
+            .unwrap();
+        bank.add_user(User::new("Bob".to_string(), 0.into(), 100.into()))
+            .unwrap();
+
+        assert!(matches!(
+            bank.transfer_funds("Alice", "Bob", 0.into()),
+            Err(TransferError::ZeroAmount)
+        ));
+        assert!(matches!(
+            bank.transfer_funds("Alice", "Alice", 10.into()),
+            Err(TransferError::SelfTransfer(_))
+        ));
+        assert_eq!(bank.ledger().len(), 2);
+    }
 
-        bank.transfer_funds("Alice", "Bob", 500).unwrap();
-        assert_eq!(bank.users[0].balance, 1500);
-        assert_eq!(bank.users[1].balance, 0);
+    #[test]
+    fn test_transfer_overflow() {
+        let mut bank = Bank::new("Test Bank".to_string(), 0, 0);
+        bank.add_user(User::new("Alice".to_string(), 0.into(), Money::MAX))
+            .unwrap();
+        bank.add_user(User::new("Bob".to_string(), 0.into(), Money::MAX))
+            .unwrap();
+
+        assert!(matches!(
+            bank.transfer_funds("Alice", "Bob", 1.into()),
+            Err(TransferError::ArithmeticOverflow(name)) if name == "Bob"
+        ));
+        assert!(matches!(
+            bank.transfer_funds("Alice", "Bob", (-5).into()),
+            Err(TransferError::NegativeAmount(_))
+        ));
+        assert_eq!(bank.users[0].balance, Money::MAX);
+    }
 
-        assert!(bank.transfer_funds("Alice", "Bob", 7500).is_err());
+    #[test]
+    fn test_accrue_interest_overflow_leaves_bank_unchanged() {
+        let mut bank = Bank::new("Test Bank".to_string(), 0, 1000);
+        bank.add_user(User::new("Alice".to_string(), 0.into(), 2000.into()))
+            .unwrap();
+        bank.add_user(User::new("Bob".to_string(), 0.into(), Money::MAX))
+            .unwrap();
+
+        let before = bank.clone();
+        assert!(matches!(
+            bank.accrue_interest(),
+            Err(TransferError::ArithmeticOverflow(_))
+        ));
+        assert_eq!(bank, before);
     }
 
     #[test]
     fn test_accrue_interest() {
         let mut bank = Bank::new("Test Bank".to_string(), 500, 1000);
-        bank.add_user(User::new("Alice".to_string(), 10000, 2000));
-        bank.add_user(User::new("Bob".to_string(), 10000, -1000));
-
-        bank.accrue_interest();
-        assert_eq!(bank.users[0].balance, 2200);
-        assert_eq!(bank.users[1].balance, -1050);
+        bank.add_user(User::new("Alice".to_string(), 10000.into(), 2000.into()))
+            .unwrap();
+        bank.add_user(User::new("Bob".to_string(), 10000.into(), (-1000).into()))
+            .unwrap();
+
+        bank.accrue_interest().unwrap();
+        assert_eq!(bank.users[0].balance, Money::from_minor(2200));
+        assert_eq!(bank.users[1].balance, Money::from_minor(-1050));
     }
 
     #[test]
     fn test_merge_bank() {
         let mut bank1 = Bank::new("Bank A".to_string(), 500, 1000);
-        bank1.add_user(User::new("Alice".to_string(), 5000, 2000));
-        bank1.add_user(User::new("Bob".to_string(), 3000, -500));
+        bank1
+            .add_user(User::new("Alice".to_string(), 5000.into(), 2000.into()))
+            .unwrap();
+        bank1
+            .add_user(User::new("Bob".to_string(), 3000.into(), (-500).into()))
+            .unwrap();
 
         let mut bank2 = Bank::new("Bank B".to_string(), 600, 900);
-        bank2.add_user(User::new("Alice".to_string(), 4000, 1000));
-        bank2.add_user(User::new("Charlie".to_string(), 2000, 1500));
+        bank2
+            .add_user(User::new("Alice".to_string(), 4000.into(), 1000.into()))
+            .unwrap();
+        bank2
+            .add_user(User::new("Charlie".to_string(), 2000.into(), 1500.into()))
+            .unwrap();
 
-        bank1.merge_bank(bank2);
+        bank1.merge_bank(bank2).unwrap();
 
         // Verify merged users
         assert_eq!(bank1.users.len(), 3);
 
         // Alice's balance and credit line should be combined (2000 + 1000)
         let alice = bank1.users.iter().find(|u| u.name == "Alice").unwrap();
-        assert_eq!(alice.balance, 3000);
-        assert_eq!(alice.credit_line, 9000);
+        assert_eq!(alice.balance, Money::from_minor(3000));
+        assert_eq!(alice.credit_line, Money::from_minor(9000));
 
         // Bob should remain unchanged
         let bob = bank1.users.iter().find(|u| u.name == "Bob").unwrap();
-        assert_eq!(bob.balance, -500);
+        assert_eq!(bob.balance, Money::from_minor(-500));
 
         // Charlie should be added
         let charlie = bank1.users.iter().find(|u| u.name == "Charlie").unwrap();
-        assert_eq!(charlie.balance, 1500);
+        assert_eq!(charlie.balance, Money::from_minor(1500));
+        assert_eq!(bank1.user("charlie").unwrap().id(), AccountId(3));
     }
 }
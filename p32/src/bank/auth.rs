@@ -0,0 +1,445 @@
+use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+
+use super::{Bank, MergeReport, Money, TransferError, normalize_name};
+
+/// Times the salted hash is re-hashed, to slow down guessing.
+///
+/// Every guarded call pays for one hash, so this trades login latency
+/// (tens of milliseconds in release builds) against the cost of guessing.
+/// Tests use far fewer rounds to stay fast.
+const ROUNDS: u32 = if cfg!(test) { 1000 } else { 200_000 };
+
+/// What a caller is allowed to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Role {
+    /// Holder of the named account; may only send money from it
+    Customer(String),
+    /// Branch staff; may move money between any accounts
+    Teller,
+    /// May do anything, including interest runs, merges and adding callers
+    Administrator,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Role::Customer(name) => write!(f, "customer of {}", name),
+            Role::Teller => write!(f, "teller"),
+            Role::Administrator => write!(f, "administrator"),
+        }
+    }
+}
+
+/// A guarded call, as checked by [`Role::permits`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation<'a> {
+    Transfer { from: &'a str },
+    AccrueInterest,
+    Merge,
+    Grant,
+}
+
+impl Role {
+    pub fn permits(&self, op: Operation) -> bool {
+        match (self, op) {
+            (Role::Administrator, _) => true,
+            (Role::Teller, Operation::Transfer { .. }) => true,
+            (Role::Customer(own), Operation::Transfer { from }) => {
+                normalize_name(own) == normalize_name(from)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Login and password presented with every call
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub login: String,
+    pub password: String,
+}
+
+impl Credentials {
+    pub fn new(login: &str, password: &str) -> Self {
+        Self {
+            login: login.to_string(),
+            password: password.to_string(),
+        }
+    }
+}
+
+// Keeps passwords out of logs and panic messages
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("login", &self.login)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A stored caller: role plus salted password hash, never the password
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Principal {
+    role: Role,
+    salt: [u8; 16],
+    hash: [u8; 32],
+}
+
+/// A [`Bank`] whose operations are only carried out for authorized callers.
+///
+/// Every call takes [`Credentials`], checked against salted SHA-256 hashes,
+/// and the caller's [`Role`] decides whether the call is permitted. Both
+/// unknown callers and refused calls fail with
+/// [`TransferError::Unauthorized`] without telling which it was. Callers are
+/// kept in memory only.
+///
+/// The hashes protect passwords if the caller table leaks, e.g. through a
+/// memory dump: each guess costs 200,000 rounds of SHA-256. Iterated
+/// SHA-256 is cheap on GPUs compared with a memory-hard function such as
+/// Argon2, so this slows down but does not stop offline guessing of weak
+/// passwords. Online guessing is not throttled here; front ends exposing
+/// the bank must rate-limit failed calls themselves.
+#[derive(Debug, Clone)]
+pub struct SecuredBank {
+    bank: Bank,
+    principals: HashMap<String, Principal>, // keyed by login
+}
+
+impl SecuredBank {
+    /// Guards `bank`, with one administrator to add further callers
+    pub fn new(bank: Bank, admin: &Credentials) -> Self {
+        let mut secured = Self {
+            bank,
+            principals: HashMap::new(),
+        };
+        secured.insert(admin, Role::Administrator);
+        secured
+    }
+
+    /// Read access needs no credentials
+    pub fn bank(&self) -> &Bank {
+        &self.bank
+    }
+
+    /// Drops the guard and returns the bank
+    pub fn into_bank(self) -> Bank {
+        self.bank
+    }
+
+    /// Adds or replaces the caller `new` with `role`; administrators only.
+    ///
+    /// A customer role must name an existing account.
+    pub fn grant(
+        &mut self,
+        caller: &Credentials,
+        new: &Credentials,
+        role: Role,
+    ) -> Result<(), TransferError> {
+        self.authorize(caller, Operation::Grant)?;
+        if let Role::Customer(name) = &role
+            && self.bank.user(name).is_none()
+        {
+            return Err(TransferError::UserNotFound(name.clone()));
+        }
+        self.insert(new, role);
+        Ok(())
+    }
+
+    /// Removes a caller; administrators only, and not their own login
+    pub fn revoke(&mut self, caller: &Credentials, login: &str) -> Result<(), TransferError> {
+        self.authorize(caller, Operation::Grant)?;
+        if login == caller.login {
+            return Err(TransferError::Unauthorized(login.to_string()));
+        }
+        match self.principals.remove(login) {
+            Some(_) => Ok(()),
+            None => Err(TransferError::UnknownLogin(login.to_string())),
+        }
+    }
+
+    /// [`Bank::transfer_funds`] for customers sending from their own account,
+    /// tellers and administrators
+    pub fn transfer_funds(
+        &mut self,
+        caller: &Credentials,
+        from: &str,
+        to: &str,
+        amount: Money,
+    ) -> Result<(), TransferError> {
+        self.authorize(caller, Operation::Transfer { from })?;
+        self.bank.transfer_funds(from, to, amount)
+    }
+
+    /// [`Bank::accrue_interest`]; administrators only
+    pub fn accrue_interest(&mut self, caller: &Credentials) -> Result<(), TransferError> {
+        self.authorize(caller, Operation::AccrueInterest)?;
+        self.bank.accrue_interest()
+    }
+
+    /// [`Bank::merge_bank`]; administrators only
+    pub fn merge_bank(
+        &mut self,
+        caller: &Credentials,
+        other: Bank,
+    ) -> Result<MergeReport, TransferError> {
+        self.authorize(caller, Operation::Merge)?;
+        self.bank.merge_bank(other)
+    }
+
+    /// Role of the caller if their password is right and it permits `op`
+    pub fn authorize(&self, caller: &Credentials, op: Operation) -> Result<&Role, TransferError> {
+        let denied = || TransferError::Unauthorized(caller.login.clone());
+        let Some(principal) = self.principals.get(&caller.login) else {
+            // Hash anyway so unknown logins take as long as wrong passwords
+            hash_password(&[0; 16], &caller.password);
+            return Err(denied());
+        };
+        let hash = hash_password(&principal.salt, &caller.password);
+        if !constant_time_eq(&hash, &principal.hash) || !principal.role.permits(op) {
+            return Err(denied());
+        }
+        Ok(&principal.role)
+    }
+
+    fn insert(&mut self, credentials: &Credentials, role: Role) {
+        let salt = new_salt();
+        let hash = hash_password(&salt, &credentials.password);
+        let principal = Principal { role, salt, hash };
+        self.principals.insert(credentials.login.clone(), principal);
+    }
+}
+
+/// 16 bytes from the randomly keyed std hasher
+fn new_salt() -> [u8; 16] {
+    let mut salt = [0; 16];
+    for chunk in salt.chunks_mut(8) {
+        let word = RandomState::new().build_hasher().finish();
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    salt
+}
+
+fn hash_password(salt: &[u8; 16], password: &str) -> [u8; 32] {
+    let mut input = salt.to_vec();
+    input.extend_from_slice(password.as_bytes());
+    let mut hash = sha256(&input);
+    // Previous hash then salt, one SHA-256 block per round
+    let mut input = [0; 48];
+    input[32..].copy_from_slice(salt);
+    for _ in 1..ROUNDS {
+        input[..32].copy_from_slice(&hash);
+        hash = sha256(&input);
+    }
+    hash
+}
+
+fn constant_time_eq(a: &[u8; 32], b: &[u8; 32]) -> bool {
+    a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256 (FIPS 180-4)
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+
+    let mut out = [0; 32];
+    for (chunk, word) in out.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::User;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn admin() -> Credentials {
+        Credentials::new("root", "correct horse")
+    }
+
+    fn secured_bank() -> SecuredBank {
+        let mut bank = Bank::new("Safe Bank".to_string(), 0, 1000);
+        bank.add_user(User::new("Alice".to_string(), 0.into(), 500.into()))
+            .unwrap();
+        bank.add_user(User::new("Bob".to_string(), 0.into(), 500.into()))
+            .unwrap();
+        let mut secured = SecuredBank::new(bank, &admin());
+        let alice = Credentials::new("alice", "wonderland");
+        secured
+            .grant(&admin(), &alice, Role::Customer("Alice".to_string()))
+            .unwrap();
+        let teller = Credentials::new("tess", "counter");
+        secured.grant(&admin(), &teller, Role::Teller).unwrap();
+        secured
+    }
+
+    #[test]
+    fn test_sha256_vectors() {
+        assert_eq!(
+            hex(&sha256(b"")),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hex(&sha256(b"abc")),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let long = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+        assert_eq!(
+            hex(&sha256(long)),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn test_passwords_are_salted() {
+        let secured = secured_bank();
+        let (a, b) = (new_salt(), new_salt());
+        assert_ne!(a, b);
+        assert_ne!(hash_password(&a, "pw"), hash_password(&b, "pw"));
+        assert_eq!(hash_password(&a, "pw"), hash_password(&a, "pw"));
+        assert!(!format!("{:?}", secured).contains("wonderland"));
+        assert!(!format!("{:?}", admin()).contains("horse"));
+    }
+
+    #[test]
+    fn test_customers_only_send_their_own_money() {
+        let mut secured = secured_bank();
+        let alice = Credentials::new("alice", "wonderland");
+        secured
+            .transfer_funds(&alice, "ALICE", "Bob", 100.into())
+            .unwrap();
+        assert!(matches!(
+            secured.transfer_funds(&alice, "Bob", "Alice", 100.into()),
+            Err(TransferError::Unauthorized(login)) if login == "alice"
+        ));
+        assert!(matches!(
+            secured.accrue_interest(&alice),
+            Err(TransferError::Unauthorized(_))
+        ));
+
+        let wrong = Credentials::new("alice", "looking glass");
+        assert!(matches!(
+            secured.transfer_funds(&wrong, "Alice", "Bob", 1.into()),
+            Err(TransferError::Unauthorized(_))
+        ));
+        let unknown = Credentials::new("mallory", "wonderland");
+        assert!(matches!(
+            secured.transfer_funds(&unknown, "Alice", "Bob", 1.into()),
+            Err(TransferError::Unauthorized(_))
+        ));
+        assert_eq!(
+            secured.bank().user("Bob").unwrap().balance(),
+            Money::from_minor(600)
+        );
+    }
+
+    #[test]
+    fn test_staff_roles() {
+        let mut secured = secured_bank();
+        let teller = Credentials::new("tess", "counter");
+        secured
+            .transfer_funds(&teller, "Bob", "Alice", 50.into())
+            .unwrap();
+        assert!(matches!(
+            secured.accrue_interest(&teller),
+            Err(TransferError::Unauthorized(_))
+        ));
+        let other = Bank::new("Other".to_string(), 0, 0);
+        assert!(matches!(
+            secured.merge_bank(&teller, other.clone()),
+            Err(TransferError::Unauthorized(_))
+        ));
+        let new = Credentials::new("eve", "x");
+        assert!(matches!(
+            secured.grant(&teller, &new, Role::Administrator),
+            Err(TransferError::Unauthorized(_))
+        ));
+
+        secured.accrue_interest(&admin()).unwrap();
+        secured.merge_bank(&admin(), other).unwrap();
+        assert!(matches!(
+            secured.grant(&admin(), &new, Role::Customer("Nobody".to_string())),
+            Err(TransferError::UserNotFound(_))
+        ));
+        secured.revoke(&admin(), "tess").unwrap();
+        assert!(matches!(
+            secured.transfer_funds(&teller, "Bob", "Alice", 1.into()),
+            Err(TransferError::Unauthorized(_))
+        ));
+        assert!(matches!(
+            secured.revoke(&admin(), "root"),
+            Err(TransferError::Unauthorized(_))
+        ));
+        assert!(matches!(
+            secured.revoke(&admin(), "tess"),
+            Err(TransferError::UnknownLogin(login)) if login == "tess"
+        ));
+    }
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use super::{Bank, Money, TransferError, User};
+
+/// One transfer instruction within a batch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transfer {
+    pub from: String,
+    pub to: String,
+    pub amount: Money,
+}
+
+impl Transfer {
+    pub fn new(from: &str, to: &str, amount: Money) -> Self {
+        Self {
+            from: from.to_string(),
+            to: to.to_string(),
+            amount,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchMode {
+    /// Apply every transfer or none of them
+    Atomic,
+    /// Apply the transfers that succeed and report the ones that fail
+    BestEffort,
+}
+
+/// Outcome of each transfer in a batch, in submission order
+#[derive(Debug)]
+pub struct BatchReport {
+    pub results: Vec<Result<(), TransferError>>,
+}
+
+impl BatchReport {
+    /// Number of transfers that were applied
+    pub fn applied(&self) -> usize {
+        self.results.iter().filter(|r| r.is_ok()).count()
+    }
+
+    /// Index and error of every transfer that was rejected
+    pub fn failures(&self) -> impl Iterator<Item = (usize, &TransferError)> {
+        self.results
+            .iter()
+            .enumerate()
+            .filter_map(|(i, r)| r.as_ref().err().map(|e| (i, e)))
+    }
+}
+
+/// An atomic batch was rolled back because of the transfer at `index`
+#[derive(Debug)]
+pub struct BatchError {
+    pub index: usize,
+    pub error: TransferError,
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Batch rolled back at transfer {}: {}",
+            self.index, self.error
+        )
+    }
+}
+
+impl Error for BatchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// State needed to undo the transfers applied so far
+struct Checkpoint {
+    users: HashMap<usize, User>,
+    fee_revenue: Money,
+    ledger_len: usize,
+}
+
+impl Checkpoint {
+    fn new(bank: &Bank) -> Self {
+        Self {
+            users: HashMap::new(),
+            fee_revenue: bank.fee_revenue,
+            ledger_len: bank.ledger.len(),
+        }
+    }
+
+    /// Remembers the user as it was before the batch touched it
+    fn save(&mut self, bank: &Bank, name: &str) {
+        if let Some(idx) = bank.position(name) {
+            self.users
+                .entry(idx)
+                .or_insert_with(|| bank.users[idx].clone());
+        }
+    }
+
+    fn restore(self, bank: &mut Bank) {
+        for (idx, user) in self.users {
+            bank.users[idx] = user;
+        }
+        bank.fee_revenue = self.fee_revenue;
+        bank.ledger.truncate(self.ledger_len);
+    }
+}
+
+impl Bank {
+    /// Applies a list of transfers as one unit.
+    ///
+    /// In [`BatchMode::Atomic`] the first failing transfer rolls back every
+    /// transfer before it and is returned as a [`BatchError`]. In
+    /// [`BatchMode::BestEffort`] failures are skipped and listed in the report.
+    pub fn transfer_batch(
+        &mut self,
+        transfers: &[Transfer],
+        mode: BatchMode,
+    ) -> Result<BatchReport, BatchError> {
+        let mut checkpoint = Checkpoint::new(self);
+        let mut results = Vec::with_capacity(transfers.len());
+
+        for (index, t) in transfers.iter().enumerate() {
+            if mode == BatchMode::Atomic {
+                checkpoint.save(self, &t.from);
+                checkpoint.save(self, &t.to);
+            }
+
+            let result = self.transfer_funds(&t.from, &t.to, t.amount);
+            match result {
+                Err(error) if mode == BatchMode::Atomic => {
+                    checkpoint.restore(self);
+                    return Err(BatchError { index, error });
+                }
+                result => results.push(result),
+            }
+        }
+
+        Ok(BatchReport { results })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payroll_bank() -> Bank {
+        let mut bank = Bank::new("Payroll Bank".to_string(), 0, 0);
+        bank.add_user(User::new("Employer".to_string(), 5000.into(), 3000.into()))
+            .unwrap();
+        bank.add_user(User::new("Alice".to_string(), 0.into(), 0.into()))
+            .unwrap();
+        bank.add_user(User::new("Bob".to_string(), 0.into(), 0.into()))
+            .unwrap();
+        bank
+    }
+
+    #[test]
+    fn test_atomic_batch_applies_all() {
+        let mut bank = payroll_bank();
+        let batch = [
+            Transfer::new("Employer", "Alice", 1500.into()),
+            Transfer::new("Employer", "Bob", 1500.into()),
+        ];
+
+        let report = bank.transfer_batch(&batch, BatchMode::Atomic).unwrap();
+        assert_eq!(report.applied(), 2);
+        assert_eq!(bank.users[0].balance, Money::from_minor(0));
+        assert_eq!(bank.users[1].balance, Money::from_minor(1500));
+        assert_eq!(bank.users[2].balance, Money::from_minor(1500));
+    }
+
+    #[test]
+    fn test_atomic_batch_rolls_back() {
+        let mut bank = payroll_bank();
+        let before = bank.clone();
+        let batch = [
+            Transfer::new("Employer", "Alice", 1500.into()),
+            Transfer::new("Employer", "Bob", 1500.into()),
+            Transfer::new("Employer", "Carol", 10.into()),
+        ];
+
+        let err = bank.transfer_batch(&batch, BatchMode::Atomic).unwrap_err();
+        assert_eq!(err.index, 2);
+        assert!(matches!(err.error, TransferError::UserNotFound(_)));
+        assert_eq!(bank, before);
+    }
+
+    #[test]
+    fn test_best_effort_batch_reports_failures() {
+        let mut bank = payroll_bank();
+        let batch = [
+            Transfer::new("Employer", "Alice", 1500.into()),
+            Transfer::new("Employer", "Carol", 10.into()),
+            Transfer::new("Employer", "Bob", 1500.into()),
+        ];
+
+        let report = bank.transfer_batch(&batch, BatchMode::BestEffort).unwrap();
+        assert_eq!(report.applied(), 2);
+        let failed: Vec<_> = report.failures().map(|(i, _)| i).collect();
+        assert_eq!(failed, vec![1]);
+        assert_eq!(bank.users[2].balance, Money::from_minor(1500));
+    }
+}
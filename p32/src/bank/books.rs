@@ -0,0 +1,380 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use super::{Bank, EntryKind, Ledger, LedgerEntry, Money};
+
+/// Accounts owned by the bank itself, the counterparty of every entry that
+/// does not move money between two users
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InternalAccount {
+    /// Interest paid to users
+    InterestExpense,
+    /// Interest charged to users
+    InterestIncome,
+    /// Funds brought in or taken out with accounts: openings, merges, splits
+    Equity,
+    /// Fees charged to users
+    FeeRevenue,
+    /// Payments to and from other banks, closed at the end of every clearing
+    /// cycle
+    Settlement,
+    /// Principal lent to users and not yet repaid
+    Loans,
+}
+
+impl fmt::Display for InternalAccount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InternalAccount::InterestExpense => write!(f, "interest expense"),
+            InternalAccount::InterestIncome => write!(f, "interest income"),
+            InternalAccount::Equity => write!(f, "equity"),
+            InternalAccount::FeeRevenue => write!(f, "fee revenue"),
+            InternalAccount::Settlement => write!(f, "settlement"),
+            InternalAccount::Loans => write!(f, "loans"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Account {
+    User(String),
+    Internal(InternalAccount),
+}
+
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Account::User(name) => f.pad(name),
+            Account::Internal(account) => f.pad(&format!("[{}]", account)),
+        }
+    }
+}
+
+/// One balanced double-entry posting: `amount` is debited from one account
+/// and credited to another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Posting {
+    pub seq: u64,
+    pub debit: Account,
+    pub credit: Account,
+    pub amount: Money,
+}
+
+impl LedgerEntry {
+    /// The entry as a posting, with the bank's side on an internal account.
+    ///
+    /// Money leaving an account is a debit and money arriving a credit, so a
+    /// user's balance is their credits minus their debits.
+    pub fn posting(&self) -> Posting {
+        let (debit, credit) = match &self.kind {
+            // Fee revenue brought in by a merge has no user on either side
+            EntryKind::Merge { .. } if self.from.is_none() && self.to.is_none() => {
+                (InternalAccount::Equity, InternalAccount::FeeRevenue)
+            }
+            EntryKind::Open { .. }
+            | EntryKind::Merge { .. }
+            | EntryKind::Split { .. }
+            | EntryKind::CreditLine { .. }
+            | EntryKind::Status { .. }
+            | EntryKind::Limits { .. }
+            | EntryKind::Transfer => (InternalAccount::Equity, InternalAccount::Equity),
+            EntryKind::Interest { .. } => (
+                InternalAccount::InterestExpense,
+                InternalAccount::InterestIncome,
+            ),
+            EntryKind::Fee { .. } => (InternalAccount::FeeRevenue, InternalAccount::FeeRevenue),
+            EntryKind::Clearing { .. } => {
+                (InternalAccount::Settlement, InternalAccount::Settlement)
+            }
+            // Money received on balance moves from equity into settlement
+            EntryKind::Settlement { net, .. } if net.is_positive() => {
+                (InternalAccount::Equity, InternalAccount::Settlement)
+            }
+            EntryKind::Settlement { .. } => (InternalAccount::Settlement, InternalAccount::Equity),
+            EntryKind::LoanDisbursement { .. } | EntryKind::LoanPrincipal { .. } => {
+                (InternalAccount::Loans, InternalAccount::Loans)
+            }
+            EntryKind::LoanInterest { .. } => (
+                InternalAccount::InterestIncome,
+                InternalAccount::InterestIncome,
+            ),
+            // A loan taken over is lent out of equity, one handed over repays it
+            EntryKind::LoanTransfer { outstanding, .. } if outstanding.is_positive() => {
+                (InternalAccount::Loans, InternalAccount::Equity)
+            }
+            EntryKind::LoanTransfer { .. } => (InternalAccount::Equity, InternalAccount::Loans),
+        };
+        let account = |name: &Option<String>, internal| match name {
+            Some(name) => Account::User(name.clone()),
+            None => Account::Internal(internal),
+        };
+        Posting {
+            seq: self.seq,
+            debit: account(&self.from, debit),
+            credit: account(&self.to, credit),
+            amount: self.amount,
+        }
+    }
+}
+
+impl Ledger {
+    pub fn postings(&self) -> impl Iterator<Item = Posting> + '_ {
+        self.iter().map(LedgerEntry::posting)
+    }
+}
+
+/// Debit and credit totals of one account, in minor units
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrialBalanceLine {
+    pub account: Account,
+    pub debits: i128,
+    pub credits: i128,
+    /// What the bank itself holds for the account, as credits minus debits:
+    /// a user's balance, the fee revenue or minus the loans outstanding
+    pub recorded: Option<i128>,
+}
+
+impl TrialBalanceLine {
+    /// Credits minus debits; for users this is their balance
+    pub fn balance(&self) -> i128 {
+        self.credits - self.debits
+    }
+
+    /// True unless the bank holds a different figure than the postings add
+    /// up to
+    pub fn reconciles(&self) -> bool {
+        self.recorded
+            .is_none_or(|recorded| recorded == self.balance())
+    }
+}
+
+/// Totals of every account that has ever been posted to.
+///
+/// Sums are kept in `i128` minor units so they cannot overflow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrialBalance {
+    pub lines: Vec<TrialBalanceLine>,
+    pub total_debits: i128,
+    pub total_credits: i128,
+}
+
+impl TrialBalance {
+    /// Trial balance of `lines`, with the totals summed from the lines
+    pub fn from_lines(lines: Vec<TrialBalanceLine>) -> Self {
+        let total_debits = lines.iter().map(|l| l.debits).sum();
+        let total_credits = lines.iter().map(|l| l.credits).sum();
+        Self {
+            lines,
+            total_debits,
+            total_credits,
+        }
+    }
+
+    /// True if total debits equal total credits and every line reconciles
+    /// with the bank's own figures
+    pub fn is_balanced(&self) -> bool {
+        self.total_debits == self.total_credits && self.lines.iter().all(|l| l.reconciles())
+    }
+
+    pub fn line(&self, account: &Account) -> Option<&TrialBalanceLine> {
+        self.lines.iter().find(|l| &l.account == account)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "{:<28}{:>18}{:>18}", "Account", "Debits", "Credits");
+        for line in &self.lines {
+            let _ = writeln!(
+                out,
+                "{:<28}{:>18}{:>18}",
+                line.account,
+                minor_units(line.debits),
+                minor_units(line.credits)
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{:<28}{:>18}{:>18}",
+            "Total",
+            minor_units(self.total_debits),
+            minor_units(self.total_credits)
+        );
+        out
+    }
+}
+
+/// Position of the account's line, adding an empty one on first use
+fn line_for(
+    lines: &mut Vec<TrialBalanceLine>,
+    index: &mut HashMap<Account, usize>,
+    account: Account,
+) -> usize {
+    *index.entry(account.clone()).or_insert_with(|| {
+        lines.push(TrialBalanceLine {
+            account,
+            debits: 0,
+            credits: 0,
+            recorded: None,
+        });
+        lines.len() - 1
+    })
+}
+
+/// Formats an i128 amount of minor units like [`Money`]
+fn minor_units(value: i128) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    format!("{}{}.{:02}", sign, value.abs() / 100, value.abs() % 100)
+}
+
+impl Bank {
+    /// Debit and credit totals of every account, from the ledger's postings,
+    /// next to the figures the bank records for users, fees and loans
+    pub fn trial_balance(&self) -> TrialBalance {
+        let mut lines: Vec<TrialBalanceLine> = [
+            InternalAccount::Equity,
+            InternalAccount::InterestExpense,
+            InternalAccount::InterestIncome,
+            InternalAccount::FeeRevenue,
+            InternalAccount::Settlement,
+            InternalAccount::Loans,
+        ]
+        .into_iter()
+        .map(|account| TrialBalanceLine {
+            account: Account::Internal(account),
+            debits: 0,
+            credits: 0,
+            recorded: None,
+        })
+        .collect();
+        let mut index: HashMap<Account, usize> = lines
+            .iter()
+            .enumerate()
+            .map(|(i, l)| (l.account.clone(), i))
+            .collect();
+
+        for posting in self.ledger.postings() {
+            let amount = posting.amount.minor() as i128;
+            let debit = line_for(&mut lines, &mut index, posting.debit);
+            lines[debit].debits += amount;
+            let credit = line_for(&mut lines, &mut index, posting.credit);
+            lines[credit].credits += amount;
+        }
+
+        // Lending debits the loans account, so it holds minus the outstanding
+        let loans: i128 = self
+            .loans
+            .iter()
+            .map(|l| l.outstanding.minor() as i128)
+            .sum();
+        let recorded = self
+            .users
+            .iter()
+            .map(|u| (Account::User(u.name.clone()), u.balance.minor() as i128))
+            .chain([
+                (
+                    Account::Internal(InternalAccount::FeeRevenue),
+                    self.fee_revenue.minor() as i128,
+                ),
+                (Account::Internal(InternalAccount::Loans), -loans),
+            ]);
+        for (account, figure) in recorded {
+            let line = line_for(&mut lines, &mut index, account);
+            lines[line].recorded = Some(figure);
+        }
+        TrialBalance::from_lines(lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::{Fee, FeeRule, FeeTrigger, testing};
+
+    fn sample_bank() -> Bank {
+        let users = [("Alice", 1000, 2000), ("Bob", 1000, 0)];
+        let mut bank = testing::bank("Books Bank", (500, 1000), &users);
+        bank.fees = vec![FeeRule::new(FeeTrigger::Transfer, Fee::Flat(5.into()))];
+        bank
+    }
+
+    fn assert_books_match(bank: &Bank) {
+        let trial = bank.trial_balance();
+        assert!(trial.is_balanced());
+        for user in bank.users() {
+            let line = trial.line(&Account::User(user.name.clone())).unwrap();
+            assert_eq!(
+                line.balance(),
+                user.balance.minor() as i128,
+                "{}",
+                user.name
+            );
+        }
+        let revenue = trial.line(&Account::Internal(InternalAccount::FeeRevenue));
+        assert_eq!(
+            revenue.unwrap().balance(),
+            bank.fee_revenue().minor() as i128
+        );
+        // Every account together always nets to zero
+        assert_eq!(trial.lines.iter().map(|l| l.balance()).sum::<i128>(), 0);
+    }
+
+    #[test]
+    fn test_every_operation_balances() {
+        let mut bank = sample_bank();
+        assert_books_match(&bank);
+
+        bank.transfer_funds("Bob", "Alice", 500.into()).unwrap();
+        assert_books_match(&bank);
+        bank.accrue_interest().unwrap();
+        assert_books_match(&bank);
+
+        let other = testing::bank("Other", (0, 0), &[("Carol", 0, 300)]);
+        bank.merge_bank(other).unwrap();
+        assert_books_match(&bank);
+        bank.split_users("Spun".to_string(), 0, 0, &["Carol"])
+            .unwrap();
+        assert_books_match(&bank);
+    }
+
+    #[test]
+    fn test_trial_balance_reconciles_with_the_bank() {
+        let mut bank = sample_bank();
+        bank.transfer_funds("Alice", "Bob", 500.into()).unwrap();
+        assert!(bank.trial_balance().is_balanced());
+
+        // Money that appears without a posting still leaves debits equal to
+        // credits, but no longer matches the books
+        bank.users[1].balance = Money::from_minor(600);
+        bank.fee_revenue = Money::ZERO;
+        let trial = bank.trial_balance();
+        assert_eq!(trial.total_debits, trial.total_credits);
+        assert!(!trial.is_balanced());
+        let unreconciled: Vec<_> = trial
+            .lines
+            .iter()
+            .filter(|l| !l.reconciles())
+            .map(|l| (l.account.to_string(), l.balance(), l.recorded))
+            .collect();
+        assert_eq!(
+            unreconciled,
+            vec![
+                ("[fee revenue]".to_string(), 5, Some(0)),
+                ("Bob".to_string(), 500, Some(600)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interest_has_internal_counterparty() {
+        let users = [("Saver", 0, 1000), ("Borrower", 1000, -1000)];
+        let mut bank = testing::bank("Books Bank", (500, 1000), &users);
+        bank.accrue_interest().unwrap();
+
+        let trial = bank.trial_balance();
+        let expense = trial.line(&Account::Internal(InternalAccount::InterestExpense));
+        let income = trial.line(&Account::Internal(InternalAccount::InterestIncome));
+        assert_eq!(expense.unwrap().debits, 100);
+        assert_eq!(income.unwrap().credits, 50);
+        assert!(trial.to_text().contains("[interest expense]"));
+    }
+}
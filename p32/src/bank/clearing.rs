@@ -0,0 +1,519 @@
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::mem;
+
+use super::{Bank, EntryKind, Money, TransferError};
+
+/// Identifier of a payment instruction within one clearing house
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PaymentId(pub u64);
+
+impl fmt::Display for PaymentId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// Instruction to pay `amount` from a user of one bank to a user of another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Payment {
+    pub from_bank: String,
+    pub from: String,
+    pub to_bank: String,
+    pub to: String,
+    pub amount: Money,
+}
+
+impl Payment {
+    pub fn new(from_bank: &str, from: &str, to_bank: &str, to: &str, amount: Money) -> Self {
+        Self {
+            from_bank: from_bank.to_string(),
+            from: from.to_string(),
+            to_bank: to_bank.to_string(),
+            to: to.to_string(),
+            amount,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ClearingError {
+    DuplicateBank(String),
+    BankNotFound(String),
+    /// Both users are in the same bank; use [`Bank::transfer_funds`]
+    SameBank(String),
+    PaymentNotFound(PaymentId),
+    /// The payment failed the sending or receiving bank's checks
+    Rejected(TransferError),
+}
+
+impl fmt::Display for ClearingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClearingError::DuplicateBank(name) => write!(f, "Bank {} is already a member", name),
+            ClearingError::BankNotFound(name) => write!(f, "Bank {} not found", name),
+            ClearingError::SameBank(name) => {
+                write!(f, "Payment within {} does not need clearing", name)
+            }
+            ClearingError::PaymentNotFound(id) => write!(f, "Payment {} not found", id),
+            ClearingError::Rejected(err) => write!(f, "Payment rejected: {}", err),
+        }
+    }
+}
+
+impl Error for ClearingError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ClearingError::Rejected(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<TransferError> for ClearingError {
+    fn from(err: TransferError) -> Self {
+        ClearingError::Rejected(err)
+    }
+}
+
+/// What one bank sent and received in a settlement cycle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetPosition {
+    pub bank: String,
+    pub sent: Money,
+    pub received: Money,
+    pub net: Money, // received - sent; positive = owed by the other banks
+}
+
+/// Outcome of one [`ClearingHouse::settle`]
+#[derive(Debug)]
+pub struct Settlement {
+    pub house: String,
+    pub cycle: u64,
+    pub settled: Vec<PaymentId>,
+    pub rejected: Vec<(PaymentId, ClearingError)>,
+    pub positions: Vec<NetPosition>, // one per member bank
+}
+
+impl Settlement {
+    /// Total of every settled payment
+    pub fn gross(&self) -> Money {
+        self.positions
+            .iter()
+            .fold(Money::ZERO, |sum, p| sum.saturating_add(p.sent))
+    }
+
+    /// Money that moves between banks after netting: the sum of all
+    /// positive net positions
+    pub fn net(&self) -> Money {
+        self.positions.iter().fold(Money::ZERO, |sum, p| {
+            sum.saturating_add(p.net.max(Money::ZERO))
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Settlement {} of {}", self.cycle, self.house);
+        let _ = writeln!(
+            out,
+            "Settled {} payments, rejected {}",
+            self.settled.len(),
+            self.rejected.len()
+        );
+        for (id, err) in &self.rejected {
+            let _ = writeln!(out, "  {}: {}", id, err);
+        }
+        let _ = writeln!(
+            out,
+            "{:<28}{:>16}{:>16}{:>16}",
+            "Bank", "Sent", "Received", "Net"
+        );
+        for p in &self.positions {
+            let _ = writeln!(
+                out,
+                "{:<28}{:>16}{:>16}{:>16}",
+                p.bank, p.sent, p.received, p.net
+            );
+        }
+        let _ = writeln!(out, "Gross {}, net {}", self.gross(), self.net());
+        out
+    }
+}
+
+/// Clears payments between users of its member banks.
+///
+/// Payments are queued with [`ClearingHouse::submit`] and executed together
+/// by [`ClearingHouse::settle`]. Each bank posts every payment against its
+/// settlement account; at the end of the cycle the account's net balance is
+/// closed to the bank's equity, which is the amount that moves between banks.
+#[derive(Debug, Clone)]
+pub struct ClearingHouse {
+    pub name: String,
+    banks: Vec<Bank>,
+    pending: Vec<(PaymentId, Payment)>,
+    next_id: u64,
+    cycle: u64, // settlements run so far
+}
+
+impl ClearingHouse {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            banks: Vec::new(),
+            pending: Vec::new(),
+            next_id: 1,
+            cycle: 0,
+        }
+    }
+
+    /// Adds a member bank; names must be unique
+    pub fn add_bank(&mut self, bank: Bank) -> Result<(), ClearingError> {
+        if self.position(&bank.name).is_some() {
+            return Err(ClearingError::DuplicateBank(bank.name));
+        }
+        self.banks.push(bank);
+        Ok(())
+    }
+
+    /// Takes a bank out of the house; its pending payments are rejected at
+    /// the next settlement
+    pub fn remove_bank(&mut self, name: &str) -> Option<Bank> {
+        let idx = self.position(name)?;
+        Some(self.banks.remove(idx))
+    }
+
+    pub fn banks(&self) -> &[Bank] {
+        &self.banks
+    }
+
+    pub fn bank(&self, name: &str) -> Option<&Bank> {
+        self.position(name).map(|idx| &self.banks[idx])
+    }
+
+    pub fn bank_mut(&mut self, name: &str) -> Option<&mut Bank> {
+        self.position(name).map(|idx| &mut self.banks[idx])
+    }
+
+    /// Payments waiting for the next settlement, in submission order
+    pub fn pending(&self) -> &[(PaymentId, Payment)] {
+        &self.pending
+    }
+
+    /// Queues a payment and returns its id.
+    ///
+    /// Both banks and users must exist and the amount must be positive;
+    /// funds are only checked at settlement.
+    pub fn submit(&mut self, payment: Payment) -> Result<PaymentId, ClearingError> {
+        let (from_bank, to_bank) = self.banks_of(&payment)?;
+        let from = self.banks[from_bank].position(&payment.from);
+        from.ok_or_else(|| TransferError::UserNotFound(payment.from.clone()))?;
+        let to = self.banks[to_bank].position(&payment.to);
+        to.ok_or_else(|| TransferError::UserNotFound(payment.to.clone()))?;
+        if payment.amount.is_zero() {
+            return Err(TransferError::ZeroAmount.into());
+        }
+        if payment.amount.is_negative() {
+            return Err(TransferError::NegativeAmount(payment.amount).into());
+        }
+
+        let id = PaymentId(self.next_id);
+        self.next_id += 1;
+        self.pending.push((id, payment));
+        Ok(id)
+    }
+
+    pub fn cancel(&mut self, id: PaymentId) -> Result<Payment, ClearingError> {
+        let idx = self
+            .pending
+            .iter()
+            .position(|(p, _)| *p == id)
+            .ok_or(ClearingError::PaymentNotFound(id))?;
+        Ok(self.pending.remove(idx).1)
+    }
+
+    /// Executes every pending payment and nets the results per bank.
+    ///
+    /// Payments run in submission order, each one checked like a transfer
+    /// against the sender's funds, fees and transfer limits at that point;
+    /// those that fail are rejected and leave no trace. Every bank that sent or received anything then records a
+    /// `Settlement` entry for its net position. Net positions always sum to
+    /// zero.
+    pub fn settle(&mut self) -> Settlement {
+        self.cycle += 1;
+        let mut positions: Vec<NetPosition> = self
+            .banks
+            .iter()
+            .map(|bank| NetPosition {
+                bank: bank.name.clone(),
+                sent: Money::ZERO,
+                received: Money::ZERO,
+                net: Money::ZERO,
+            })
+            .collect();
+
+        let mut settled = Vec::new();
+        let mut rejected = Vec::new();
+        for (id, payment) in mem::take(&mut self.pending) {
+            match self.clear(&payment, &mut positions) {
+                Ok(()) => settled.push(id),
+                Err(err) => rejected.push((id, err)),
+            }
+        }
+
+        for (bank, position) in self.banks.iter_mut().zip(&mut positions) {
+            if position.sent.is_zero() && position.received.is_zero() {
+                continue;
+            }
+            // Both sums are non-negative, so the difference cannot overflow
+            position.net = position.received.saturating_add(-position.sent);
+            let kind = EntryKind::Settlement {
+                house: self.name.clone(),
+                net: position.net,
+            };
+            bank.record(kind, None, None, position.net.abs());
+        }
+
+        Settlement {
+            house: self.name.clone(),
+            cycle: self.cycle,
+            settled,
+            rejected,
+            positions,
+        }
+    }
+
+    /// Checks and posts one payment in both banks
+    fn clear(
+        &mut self,
+        payment: &Payment,
+        positions: &mut [NetPosition],
+    ) -> Result<(), ClearingError> {
+        let (from_bank, to_bank) = self.banks_of(payment)?;
+        let amount = payment.amount;
+
+        let bank = &self.banks[from_bank];
+        let from = bank
+            .position(&payment.from)
+            .ok_or_else(|| TransferError::UserNotFound(payment.from.clone()))?;
+        let (from_balance, fees) = bank.check_outgoing(from, amount)?;
+        let sender = &bank.users[from];
+        let sent = positions[from_bank].sent.checked_add(amount);
+        let sent = sent.ok_or(sender.overflow())?;
+
+        let bank = &self.banks[to_bank];
+        let to = bank
+            .position(&payment.to)
+            .ok_or_else(|| TransferError::UserNotFound(payment.to.clone()))?;
+        let receiver = &bank.users[to];
+        receiver.check_open()?;
+        let to_balance = receiver.balance.checked_add(amount);
+        let to_balance = to_balance.ok_or(receiver.overflow())?;
+        let received = positions[to_bank].received.checked_add(amount);
+        let received = received.ok_or(receiver.overflow())?;
+
+        let (sender, receiver) = (sender.name.clone(), receiver.name.clone());
+        let kind = EntryKind::Clearing {
+            bank: self.banks[to_bank].name.clone(),
+            user: receiver,
+        };
+        let bank = &mut self.banks[from_bank];
+        bank.users[from].balance = from_balance;
+        bank.record(kind, Some(from), None, amount);
+        bank.settle_outgoing(from, amount, fees);
+        positions[from_bank].sent = sent;
+
+        let kind = EntryKind::Clearing {
+            bank: bank.name.clone(),
+            user: sender,
+        };
+        let bank = &mut self.banks[to_bank];
+        bank.users[to].balance = to_balance;
+        bank.record(kind, None, Some(to), amount);
+        positions[to_bank].received = received;
+        Ok(())
+    }
+
+    fn banks_of(&self, payment: &Payment) -> Result<(usize, usize), ClearingError> {
+        let from = self
+            .position(&payment.from_bank)
+            .ok_or_else(|| ClearingError::BankNotFound(payment.from_bank.clone()))?;
+        let to = self
+            .position(&payment.to_bank)
+            .ok_or_else(|| ClearingError::BankNotFound(payment.to_bank.clone()))?;
+        if from == to {
+            return Err(ClearingError::SameBank(payment.from_bank.clone()));
+        }
+        Ok((from, to))
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.banks.iter().position(|b| b.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::{Account, InternalAccount, Limit, TransferLimits, User};
+
+    fn member(name: &str, users: &[(&str, i64)]) -> Bank {
+        let mut bank = Bank::new(name.to_string(), 0, 0);
+        for &(user, balance) in users {
+            bank.add_user(User::new(user.to_string(), 0.into(), balance.into()))
+                .unwrap();
+        }
+        bank
+    }
+
+    fn sample_house() -> ClearingHouse {
+        let mut house = ClearingHouse::new("Clearing".to_string());
+        house.add_bank(member("A", &[("Alice", 500)])).unwrap();
+        house.add_bank(member("B", &[("Bob", 300)])).unwrap();
+        house.add_bank(member("C", &[("Carol", 0)])).unwrap();
+        house
+    }
+
+    #[test]
+    fn test_settle_nets_positions() {
+        let mut house = sample_house();
+        house
+            .submit(Payment::new("A", "Alice", "B", "Bob", 200.into()))
+            .unwrap();
+        house
+            .submit(Payment::new("B", "Bob", "C", "Carol", 400.into()))
+            .unwrap();
+        house
+            .submit(Payment::new("C", "carol", "A", "alice", 100.into()))
+            .unwrap();
+        let broke = house
+            .submit(Payment::new("C", "Carol", "A", "Alice", 301.into()))
+            .unwrap();
+
+        let settlement = house.settle();
+        assert_eq!(settlement.settled.len(), 3);
+        assert!(matches!(
+            settlement.rejected.as_slice(),
+            [(id, ClearingError::Rejected(TransferError::InsufficientFunds(_)))] if *id == broke
+        ));
+        let nets: Vec<_> = settlement.positions.iter().map(|p| p.net.minor()).collect();
+        assert_eq!(nets, vec![-100, -200, 300]);
+        assert_eq!(settlement.gross(), Money::from_minor(700));
+        assert_eq!(settlement.net(), Money::from_minor(300));
+        assert!(house.pending().is_empty());
+
+        let balance =
+            |bank: &str, user: &str| house.bank(bank).unwrap().user(user).unwrap().balance();
+        assert_eq!(balance("A", "Alice"), Money::from_minor(400));
+        assert_eq!(balance("B", "Bob"), Money::from_minor(100));
+        assert_eq!(balance("C", "Carol"), Money::from_minor(300));
+
+        for bank in house.banks() {
+            assert!(bank.verify().is_empty(), "{}", bank.name);
+            let trial = bank.trial_balance();
+            let settlement = trial.line(&Account::Internal(InternalAccount::Settlement));
+            assert_eq!(settlement.unwrap().balance(), 0);
+
+            let mut rebuilt = Bank::new(bank.name.clone(), 0, 0);
+            rebuilt.replay(bank.ledger()).unwrap();
+            assert_eq!(&rebuilt, bank);
+        }
+        let statement = house.bank("A").unwrap().statement("Alice", 0..u64::MAX);
+        assert_eq!(
+            statement.unwrap().lines[1].description,
+            "payment to Bob at B"
+        );
+    }
+
+    #[test]
+    fn test_payments_follow_transfer_limits() {
+        let mut house = sample_house();
+        let limits = TransferLimits {
+            per_transfer: Some(100.into()),
+            daily_count: Some(2),
+            ..TransferLimits::default()
+        };
+        let bank = house.bank_mut("A").unwrap();
+        bank.set_transfer_limits("Alice", limits).unwrap();
+
+        let ids: Vec<_> = [101, 100, 50, 10]
+            .into_iter()
+            .map(|amount| {
+                let payment = Payment::new("A", "Alice", "B", "Bob", amount.into());
+                house.submit(payment).unwrap()
+            })
+            .collect();
+        let settlement = house.settle();
+        assert_eq!(settlement.settled, vec![ids[1], ids[2]]);
+        let refused: Vec<_> = settlement
+            .rejected
+            .iter()
+            .map(|(id, err)| match err {
+                ClearingError::Rejected(TransferError::LimitExceeded { limit, .. }) => {
+                    (*id, *limit)
+                }
+                other => panic!("expected a limit error, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            refused,
+            vec![
+                (ids[0], Limit::PerTransfer(100.into())),
+                (ids[3], Limit::DailyCount(0)),
+            ]
+        );
+        let alice = house.bank("A").unwrap().user("Alice").unwrap();
+        assert_eq!(alice.usage_on(0).count, 2);
+    }
+
+    #[test]
+    fn test_submit_validates_instructions() {
+        let mut house = sample_house();
+        assert!(matches!(
+            house.add_bank(member("A", &[])),
+            Err(ClearingError::DuplicateBank(_))
+        ));
+        assert!(matches!(
+            house.submit(Payment::new("A", "Alice", "Z", "Zed", 1.into())),
+            Err(ClearingError::BankNotFound(name)) if name == "Z"
+        ));
+        assert!(matches!(
+            house.submit(Payment::new("A", "Alice", "A", "Alice", 1.into())),
+            Err(ClearingError::SameBank(_))
+        ));
+        assert!(matches!(
+            house.submit(Payment::new("A", "Alice", "B", "Zed", 1.into())),
+            Err(ClearingError::Rejected(TransferError::UserNotFound(_)))
+        ));
+        assert!(matches!(
+            house.submit(Payment::new("A", "Alice", "B", "Bob", 0.into())),
+            Err(ClearingError::Rejected(TransferError::ZeroAmount))
+        ));
+
+        let id = house
+            .submit(Payment::new("A", "Alice", "B", "Bob", 10.into()))
+            .unwrap();
+        assert_eq!(house.cancel(id).unwrap().amount, Money::from_minor(10));
+        assert!(matches!(
+            house.cancel(id),
+            Err(ClearingError::PaymentNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_removed_bank_rejects_payments() {
+        let mut house = sample_house();
+        house
+            .submit(Payment::new("A", "Alice", "C", "Carol", 50.into()))
+            .unwrap();
+        let removed = house.remove_bank("C").unwrap();
+
+        let settlement = house.settle();
+        assert!(matches!(
+            settlement.rejected.as_slice(),
+            [(_, ClearingError::BankNotFound(_))]
+        ));
+        assert_eq!(settlement.gross(), Money::ZERO);
+        assert_eq!(house.bank("A").unwrap().ledger().len(), 1);
+        assert_eq!(removed.ledger().len(), 1);
+    }
+}
@@ -0,0 +1,242 @@
+use std::mem;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use super::{Bank, EntryKind, Ledger, Money, TransferError, User, check_transfer, fees, limits};
+
+/// A [`Bank`] that can be shared between threads.
+///
+/// Every account sits behind its own lock, so transfers between unrelated
+/// users run in parallel. A transfer locks its two accounts in index order
+/// and checks everything under those locks alone. Only then does it briefly
+/// lock the fee revenue to reserve its fees, and after that the ledger to
+/// append its entries; the two are never held together, which rules out
+/// deadlocks between transfers.
+///
+/// The set of users is fixed while the bank is shared; convert back with
+/// [`ConcurrentBank::into_bank`] for other operations.
+#[derive(Debug)]
+pub struct ConcurrentBank {
+    bank: Bank, // name, rates and user index; users and ledger live below
+    accounts: Vec<Mutex<User>>,
+    ledger: Mutex<Ledger>,
+    fee_revenue: Mutex<Money>,
+}
+
+impl From<Bank> for ConcurrentBank {
+    fn from(mut bank: Bank) -> Self {
+        let accounts = mem::take(&mut bank.users)
+            .into_iter()
+            .map(Mutex::new)
+            .collect();
+        let ledger = Mutex::new(mem::take(&mut bank.ledger));
+        let fee_revenue = Mutex::new(mem::take(&mut bank.fee_revenue));
+        Self {
+            bank,
+            accounts,
+            ledger,
+            fee_revenue,
+        }
+    }
+}
+
+impl ConcurrentBank {
+    pub fn name(&self) -> &str {
+        &self.bank.name
+    }
+
+    /// Current balance of the named user
+    pub fn balance(&self, name: &str) -> Option<Money> {
+        let idx = self.bank.position(name)?;
+        Some(lock(&self.accounts[idx]).balance)
+    }
+
+    /// Sum of every balance plus the fee revenue, taken while all accounts
+    /// are locked
+    pub fn total_balance(&self) -> Option<Money> {
+        let guards: Vec<_> = self.accounts.iter().map(lock).collect();
+        let revenue = *lock(&self.fee_revenue);
+        guards
+            .iter()
+            .try_fold(revenue, |sum, user| sum.checked_add(user.balance))
+    }
+
+    /// Same rules, fees and ledger entries as [`Bank::transfer_funds`]
+    pub fn transfer_funds(
+        &self,
+        from_name: &str,
+        to_name: &str,
+        amount: Money,
+    ) -> Result<(), TransferError> {
+        let from_idx = self
+            .bank
+            .position(from_name)
+            .ok_or(TransferError::UserNotFound(from_name.to_string()))?;
+
+        let to_idx = self
+            .bank
+            .position(to_name)
+            .ok_or(TransferError::UserNotFound(to_name.to_string()))?;
+
+        if from_idx == to_idx {
+            return Err(TransferError::SelfTransfer(from_name.to_string()));
+        }
+
+        // Always lock the lower index first
+        let (mut from, mut to) = if from_idx < to_idx {
+            let from = lock(&self.accounts[from_idx]);
+            (from, lock(&self.accounts[to_idx]))
+        } else {
+            let to = lock(&self.accounts[to_idx]);
+            (lock(&self.accounts[from_idx]), to)
+        };
+
+        let fees = fees::transfer_fees(&self.bank.fees, &from, amount)?;
+        let fee = fees::fee_total(&fees, &from, Money::ZERO)?;
+        let (from_balance, to_balance) = check_transfer(&from, &to, amount, fee)?;
+        limits::check(&from, amount, self.bank.today)?;
+
+        // Reserve the fees; nothing below can fail
+        if !fee.is_zero() {
+            let mut revenue = lock(&self.fee_revenue);
+            *revenue = revenue.checked_add(fee).ok_or(from.overflow())?;
+        }
+        from.balance = from_balance;
+        limits::record(&mut from, amount, self.bank.today);
+        to.balance = to_balance;
+
+        let mut ledger = lock(&self.ledger);
+        ledger.push(EntryKind::Transfer, Some(&from), Some(&to), amount);
+        for (trigger, fee) in fees {
+            fees::charge(&mut from, &mut ledger, trigger, fee);
+        }
+        Ok(())
+    }
+
+    /// Takes the accounts and ledger back into a plain [`Bank`]
+    pub fn into_bank(self) -> Bank {
+        let mut bank = self.bank;
+        bank.users = self
+            .accounts
+            .into_iter()
+            .map(|m| m.into_inner().unwrap_or_else(PoisonError::into_inner))
+            .collect();
+        bank.ledger = self
+            .ledger
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        bank.fee_revenue = self
+            .fee_revenue
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        bank
+    }
+}
+
+/// Locks ignoring poisoning: balances are only written after all checks pass
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::{Fee, FeeRule, FeeTrigger};
+    use std::sync::Arc;
+    use std::thread;
+
+    fn shared_bank(users: usize) -> ConcurrentBank {
+        let mut bank = Bank::new("Shared Bank".to_string(), 0, 0);
+        for i in 0..users {
+            let user = User::new(format!("User{}", i), 500.into(), 1000.into());
+            bank.add_user(user).unwrap();
+        }
+        ConcurrentBank::from(bank)
+    }
+
+    #[test]
+    fn test_transfer_and_back() {
+        let bank = shared_bank(2);
+        bank.transfer_funds("User0", "User1", 250.into()).unwrap();
+        assert_eq!(bank.balance("User0"), Some(Money::from_minor(750)));
+        assert!(matches!(
+            bank.transfer_funds("User0", "User1", 2000.into()),
+            Err(TransferError::CreditLimitExceeded(_))
+        ));
+
+        let bank = bank.into_bank();
+        assert_eq!(bank.users()[1].balance, Money::from_minor(1250));
+        assert_eq!(bank.ledger().len(), 3);
+    }
+
+    #[test]
+    fn test_transfer_fees() {
+        let mut bank = shared_bank(2).into_bank();
+        bank.fees = vec![FeeRule::new(FeeTrigger::Transfer, Fee::Percent(1000))];
+        let bank = ConcurrentBank::from(bank);
+
+        bank.transfer_funds("User0", "User1", 300.into()).unwrap();
+        assert_eq!(bank.balance("User0"), Some(Money::from_minor(670)));
+        assert!(bank.transfer_funds("User0", "User1", 1100.into()).is_err());
+
+        let bank = bank.into_bank();
+        assert_eq!(bank.fee_revenue(), Money::from_minor(30));
+        let mut rebuilt = Bank::new("Shared Bank".to_string(), 0, 0);
+        rebuilt.fees = bank.fees.clone();
+        rebuilt.replay(bank.ledger()).unwrap();
+        assert_eq!(rebuilt, bank);
+    }
+
+    #[test]
+    fn test_concurrent_transfers_conserve_money() {
+        const USERS: usize = 16;
+        const THREADS: u64 = 8;
+        const TRANSFERS: u64 = 2000;
+
+        let mut bank = shared_bank(USERS).into_bank();
+        let fee = FeeRule::new(FeeTrigger::Transfer, Fee::Flat(Money::from_minor(1)));
+        bank.fees = vec![fee];
+        let bank = Arc::new(ConcurrentBank::from(bank));
+        let total = bank.total_balance().unwrap();
+
+        let handles: Vec<_> = (0..THREADS)
+            .map(|t| {
+                let bank = Arc::clone(&bank);
+                thread::spawn(move || {
+                    // Simple LCG so every thread hits a different mix of pairs
+                    let mut seed = t * 7919 + 1;
+                    let mut next = move || {
+                        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                        seed >> 33
+                    };
+                    let mut applied = 0;
+                    for _ in 0..TRANSFERS {
+                        let from = format!("User{}", next() % USERS as u64);
+                        let to = format!("User{}", next() % USERS as u64);
+                        let amount = Money::from_minor((next() % 400) as i64);
+                        if bank.transfer_funds(&from, &to, amount).is_ok() {
+                            applied += 1;
+                        }
+                    }
+                    applied
+                })
+            })
+            .collect();
+        let applied: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+
+        // Fees move money into the bank's revenue but never create or lose it
+        assert_eq!(bank.total_balance(), Some(total));
+
+        let bank = Arc::into_inner(bank).unwrap().into_bank();
+        assert_eq!(bank.fee_revenue(), Money::from_minor(applied as i64));
+        assert_eq!(bank.ledger().len(), USERS + 2 * applied);
+        for user in bank.users() {
+            assert!(user.balance >= -user.credit_line);
+        }
+
+        // The ledger written from many threads still replays to the same state
+        let mut rebuilt = Bank::new("Shared Bank".to_string(), 0, 0);
+        rebuilt.fees = bank.fees.clone();
+        rebuilt.replay(bank.ledger()).unwrap();
+        assert_eq!(rebuilt, bank);
+    }
+}
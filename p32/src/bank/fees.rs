@@ -0,0 +1,319 @@
+use std::fmt;
+
+use super::{AccountStatus, Bank, EntryKind, Ledger, Money, Rounding, TransferError, User};
+
+/// How much a fee rule charges for a base amount
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fee {
+    Flat(Money),
+    /// Share of the base amount in basis points, rounded half up
+    Percent(u64),
+    /// Charged by the first tier whose `up_to` is at least the base amount
+    Tiered(Vec<FeeTier>),
+    /// Another fee kept between `min` and `max`
+    Capped {
+        fee: Box<Fee>,
+        min: Money,
+        max: Money,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeTier {
+    pub up_to: Option<Money>, // None = no upper bound
+    pub fee: Fee,
+}
+
+/// When a fee rule applies and what its base amount is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeTrigger {
+    /// Every transfer, charged to the sender on the transferred amount
+    Transfer,
+    /// A transfer that takes the sender from zero or above to below zero,
+    /// charged on the overdrawn amount
+    Overdraft,
+    /// Every `every` days of the bank clock, charged on each positive balance
+    Maintenance { every: u64 },
+}
+
+impl fmt::Display for FeeTrigger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FeeTrigger::Transfer => write!(f, "transfer"),
+            FeeTrigger::Overdraft => write!(f, "overdraft"),
+            FeeTrigger::Maintenance { .. } => write!(f, "maintenance"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeRule {
+    pub trigger: FeeTrigger,
+    pub fee: Fee,
+}
+
+impl FeeRule {
+    pub fn new(trigger: FeeTrigger, fee: Fee) -> Self {
+        Self { trigger, fee }
+    }
+}
+
+/// A maintenance fee that could not be charged to `name` on `day`
+#[derive(Debug)]
+pub struct FeeFailure {
+    pub name: String,
+    pub day: u64,
+    pub error: TransferError,
+}
+
+impl Fee {
+    /// Fee for `base`, never negative; `None` on overflow
+    pub fn amount(&self, base: Money) -> Option<Money> {
+        let fee = match self {
+            Fee::Flat(fee) => *fee,
+            Fee::Percent(bps) => base.abs().checked_mul_bps(*bps, Rounding::HalfUp)?,
+            Fee::Tiered(tiers) => {
+                let tier = tiers
+                    .iter()
+                    .find(|t| t.up_to.is_none_or(|up_to| base <= up_to));
+                match tier {
+                    Some(tier) => tier.fee.amount(base)?,
+                    None => Money::ZERO,
+                }
+            }
+            Fee::Capped { fee, min, max } => fee.amount(base)?.max(*min).min(*max),
+        };
+        Some(fee.max(Money::ZERO))
+    }
+}
+
+/// Fees owed by `from` for sending `amount`, one per rule that charges anything
+pub(super) fn transfer_fees(
+    rules: &[FeeRule],
+    from: &User,
+    amount: Money,
+) -> Result<Vec<(FeeTrigger, Money)>, TransferError> {
+    // Invalid amounts are reported by check_transfer
+    if !amount.is_positive() {
+        return Ok(Vec::new());
+    }
+    let after = from.balance.checked_sub(amount).ok_or(from.overflow())?;
+    let overdrawn = if !from.balance.is_negative() && after.is_negative() {
+        Some(after.abs())
+    } else {
+        None
+    };
+
+    let mut fees = Vec::new();
+    for rule in rules {
+        let base = match rule.trigger {
+            FeeTrigger::Transfer => amount,
+            FeeTrigger::Overdraft => match overdrawn {
+                Some(overdrawn) => overdrawn,
+                None => continue,
+            },
+            FeeTrigger::Maintenance { .. } => continue,
+        };
+        let fee = rule.fee.amount(base).ok_or(from.overflow())?;
+        if !fee.is_zero() {
+            fees.push((rule.trigger, fee));
+        }
+    }
+    Ok(fees)
+}
+
+/// Sum of `fees`, checked so it can also be added to the bank's revenue
+pub(super) fn fee_total(
+    fees: &[(FeeTrigger, Money)],
+    from: &User,
+    revenue: Money,
+) -> Result<Money, TransferError> {
+    let total = fees
+        .iter()
+        .try_fold(Money::ZERO, |sum, (_, fee)| sum.checked_add(*fee))
+        .ok_or(from.overflow())?;
+    revenue.checked_add(total).ok_or(from.overflow())?;
+    Ok(total)
+}
+
+/// Takes `fee` from `user` and records it; the caller adds it to the bank's
+/// fee revenue.
+///
+/// Cannot fail: callers check beforehand that neither the balance nor the
+/// revenue overflows, as [`fee_total`] and `check_withdrawal` do for
+/// transfers.
+pub(super) fn charge(user: &mut User, ledger: &mut Ledger, trigger: FeeTrigger, fee: Money) {
+    user.balance = user.balance.saturating_add(-fee);
+    ledger.push(EntryKind::Fee { trigger }, Some(user), None, fee);
+}
+
+impl Bank {
+    /// Fees collected so far, held by the bank itself
+    pub fn fee_revenue(&self) -> Money {
+        self.fee_revenue
+    }
+
+    /// Charges every maintenance rule due on the current day of the clock.
+    ///
+    /// A fee never takes an account past its credit line: users who cannot
+    /// pay in full are charged what they have available. Closed accounts
+    /// are not charged. Fees that would overflow the bank's revenue are not
+    /// charged either and are returned as failures.
+    pub(super) fn charge_maintenance_fees(&mut self) -> Vec<FeeFailure> {
+        let mut failed = Vec::new();
+        for rule in 0..self.fees.len() {
+            let FeeTrigger::Maintenance { every } = self.fees[rule].trigger else {
+                continue;
+            };
+            if every == 0 || !self.today.is_multiple_of(every) {
+                continue;
+            }
+            for idx in 0..self.users.len() {
+                let user = &mut self.users[idx];
+                if user.status == AccountStatus::Closed {
+                    continue;
+                }
+                let base = user.balance.max(Money::ZERO);
+                let fee = self.fees[rule].fee.amount(base).unwrap_or(Money::MAX);
+                let fee = fee.min(user.available_funds().max(Money::ZERO));
+                if fee.is_zero() {
+                    continue;
+                }
+                match self.fee_revenue.checked_add(fee) {
+                    Some(revenue) => {
+                        charge(user, &mut self.ledger, self.fees[rule].trigger, fee);
+                        self.fee_revenue = revenue;
+                    }
+                    None => failed.push(FeeFailure {
+                        name: user.name.clone(),
+                        day: self.today,
+                        error: TransferError::ArithmeticOverflow(self.name.clone()),
+                    }),
+                }
+            }
+        }
+        failed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::{BatchMode, Transfer, testing};
+
+    fn fee_bank(rules: Vec<FeeRule>) -> Bank {
+        let mut bank = testing::bank("Fee Bank", (0, 0), &[("Alice", 1000, 500), ("Bob", 0, 0)]);
+        bank.fees = rules;
+        bank
+    }
+
+    fn net_position(bank: &Bank) -> i64 {
+        let (liabilities, assets) = bank.calc_balance();
+        liabilities.minor() - assets.minor()
+    }
+
+    #[test]
+    fn test_fee_amounts() {
+        let tiered = Fee::Tiered(vec![
+            FeeTier {
+                up_to: Some(1000.into()),
+                fee: Fee::Flat(10.into()),
+            },
+            FeeTier {
+                up_to: None,
+                fee: Fee::Percent(100),
+            },
+        ]);
+        assert_eq!(tiered.amount(1000.into()), Some(Money::from_minor(10)));
+        assert_eq!(tiered.amount(5050.into()), Some(Money::from_minor(51)));
+
+        let capped = Fee::Capped {
+            fee: Box::new(Fee::Percent(250)),
+            min: 5.into(),
+            max: 100.into(),
+        };
+        assert_eq!(capped.amount(20.into()), Some(Money::from_minor(5)));
+        assert_eq!(capped.amount(2000.into()), Some(Money::from_minor(50)));
+        assert_eq!(capped.amount(10_000.into()), Some(Money::from_minor(100)));
+        assert_eq!(Fee::Flat((-5).into()).amount(0.into()), Some(Money::ZERO));
+    }
+
+    #[test]
+    fn test_transfer_and_overdraft_fees() {
+        let mut bank = fee_bank(vec![
+            FeeRule::new(FeeTrigger::Transfer, Fee::Flat(10.into())),
+            FeeRule::new(FeeTrigger::Overdraft, Fee::Percent(1000)),
+        ]);
+        let net = net_position(&bank);
+
+        bank.transfer_funds("Alice", "Bob", 400.into()).unwrap();
+        assert_eq!(bank.user("Alice").unwrap().balance, Money::from_minor(90));
+        bank.transfer_funds("Alice", "Bob", 290.into()).unwrap();
+        assert_eq!(bank.user("Alice").unwrap().balance, Money::from_minor(-230));
+        assert_eq!(bank.fee_revenue(), Money::from_minor(40));
+        assert_eq!(net_position(&bank), net);
+
+        // Fees count against the credit line too
+        assert!(matches!(
+            bank.transfer_funds("Alice", "Bob", 765.into()),
+            Err(TransferError::CreditLimitExceeded(_))
+        ));
+        assert!(matches!(
+            bank.transfer_funds("Bob", "Alice", 690.into()),
+            Err(TransferError::InsufficientFunds(_))
+        ));
+
+        let mut rebuilt = Bank::new("Fee Bank".to_string(), 0, 0);
+        rebuilt.fees = bank.fees.clone();
+        rebuilt.replay(bank.ledger()).unwrap();
+        assert_eq!(rebuilt, bank);
+    }
+
+    #[test]
+    fn test_fee_overflow_leaves_transfer_unapplied() {
+        let rule = FeeRule::new(FeeTrigger::Transfer, Fee::Flat(10.into()));
+        let mut bank = fee_bank(vec![rule]);
+        bank.fee_revenue = Money::MAX;
+        let before = bank.clone();
+
+        assert!(matches!(
+            bank.transfer_funds("Alice", "Bob", 100.into()),
+            Err(TransferError::ArithmeticOverflow(_))
+        ));
+        let transfers = vec![Transfer::new("Alice", "Bob", 100.into())];
+        assert!(bank.transfer_batch(&transfers, BatchMode::Atomic).is_err());
+        assert_eq!(bank, before);
+    }
+
+    #[test]
+    fn test_maintenance_fees() {
+        let rule = FeeRule::new(FeeTrigger::Maintenance { every: 30 }, Fee::Flat(300.into()));
+        let mut bank = fee_bank(vec![rule]);
+        bank.transfer_funds("Alice", "Bob", 100.into()).unwrap();
+        let net = net_position(&bank);
+
+        bank.advance_clock(29);
+        assert!(bank.fee_revenue().is_zero());
+        bank.advance_clock(1);
+        assert_eq!(bank.user("Alice").unwrap().balance, Money::from_minor(100));
+        assert_eq!(bank.user("Bob").unwrap().balance, Money::ZERO);
+        assert_eq!(bank.fee_revenue(), Money::from_minor(400));
+
+        bank.advance_clock(30);
+        assert_eq!(bank.user("Alice").unwrap().balance, Money::from_minor(-200));
+        assert_eq!(net_position(&bank), net);
+
+        // A fee the revenue cannot take is reported, not silently waived
+        bank.fee_revenue = Money::MAX;
+        let report = bank.advance_clock(30);
+        assert!(report.runs.is_empty());
+        let failures = report.fee_failures;
+        assert_eq!(failures.len(), 1);
+        assert_eq!((failures[0].name.as_str(), failures[0].day), ("Alice", 90));
+        assert!(matches!(
+            &failures[0].error,
+            TransferError::ArithmeticOverflow(name) if name == "Fee Bank"
+        ));
+        assert_eq!(bank.user("Alice").unwrap().balance, Money::from_minor(-200));
+    }
+}
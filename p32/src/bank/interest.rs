@@ -0,0 +1,222 @@
+use super::{AccountStatus, Bank, EntryKind, Money, TransferError};
+
+/// Fractions of a minor unit tracked by [`User`](super::User) interest carry
+pub const CARRY_SCALE: i64 = 1_000_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompoundingPeriod {
+    Daily,
+    Monthly,
+    Quarterly,
+}
+
+/// How days are counted when turning an annual rate into a period rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DayCount {
+    /// 365-day year, rate divided by 365
+    Actual365,
+    /// 365-day year, rate divided by 360
+    Actual360,
+    /// Twelve 30-day months, rate divided by 360
+    Thirty360,
+}
+
+impl DayCount {
+    fn days_in_year(self) -> i128 {
+        match self {
+            DayCount::Thirty360 => 360,
+            DayCount::Actual365 | DayCount::Actual360 => 365,
+        }
+    }
+
+    fn basis(self) -> i128 {
+        match self {
+            DayCount::Actual365 => 365,
+            DayCount::Actual360 | DayCount::Thirty360 => 360,
+        }
+    }
+}
+
+/// Treats the bank's interest rates as annual rates compounded per period.
+///
+/// Each call to [`Bank::accrue_interest`] then accrues one period. Interest
+/// below one minor unit is carried per account instead of being rounded
+/// away, so small balances still accrue over time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterestSchedule {
+    pub period: CompoundingPeriod,
+    pub day_count: DayCount,
+}
+
+impl InterestSchedule {
+    pub fn new(period: CompoundingPeriod, day_count: DayCount) -> Self {
+        Self { period, day_count }
+    }
+
+    /// Number of periods (accrue_interest calls) that make up one year
+    pub fn periods_per_year(&self) -> u32 {
+        match self.period {
+            CompoundingPeriod::Daily => self.day_count.days_in_year() as u32,
+            CompoundingPeriod::Monthly => 12,
+            CompoundingPeriod::Quarterly => 4,
+        }
+    }
+
+    /// Share of the annual rate applied per period, as numerator and denominator
+    fn period_fraction(&self) -> (i128, i128) {
+        let days_in_year = self.day_count.days_in_year();
+        let periods = self.periods_per_year() as i128;
+        (days_in_year, periods * self.day_count.basis())
+    }
+}
+
+impl Bank {
+    /// Accrues one compounding period, carrying sub-unit remainders.
+    ///
+    /// Works on `balance * CARRY_SCALE + carry`, so each period's interest is
+    /// exact to a billionth of a minor unit. Only whole minor units are moved
+    /// into the balance; the rest stays in the account's carry.
+    pub(super) fn accrue_scheduled_interest(
+        &mut self,
+        schedule: InterestSchedule,
+    ) -> Result<(), TransferError> {
+        let (num, den) = schedule.period_fraction();
+        let den = den * 10_000; // rates are in basis points
+        let scale = CARRY_SCALE as i128;
+
+        let mut updates = Vec::with_capacity(self.users.len());
+        for user in &self.users {
+            if user.status == AccountStatus::Closed {
+                updates.push((user.balance, Money::ZERO, user.interest_carry));
+                continue;
+            }
+            let value = user.balance.minor() as i128 * scale + user.interest_carry as i128;
+            // Debit interest on positive balances, credit interest on negative
+            let rate = if value > 0 {
+                self.debit_interest
+            } else {
+                self.credit_interest
+            };
+
+            let value = (value * num)
+                .checked_mul(rate as i128)
+                .and_then(|v| value.checked_add(div_round_half_up(v, den)))
+                .ok_or(user.overflow())?;
+
+            let balance = i64::try_from(value.div_euclid(scale))
+                .ok()
+                .filter(|&b| b != i64::MIN)
+                .map(Money::from_minor)
+                .ok_or(user.overflow())?;
+            let moved = balance.checked_sub(user.balance).ok_or(user.overflow())?;
+            updates.push((balance, moved, value.rem_euclid(scale) as i64));
+        }
+
+        for (idx, (balance, moved, carry)) in updates.into_iter().enumerate() {
+            let user = &mut self.users[idx];
+            if user.balance != balance || user.interest_carry != carry {
+                user.balance = balance;
+                user.interest_carry = carry;
+                self.record_deposit(EntryKind::Interest { carry }, idx, moved);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn div_round_half_up(value: i128, den: i128) -> i128 {
+    let quotient = value / den;
+    if (value % den).abs() * 2 >= den {
+        quotient + value.signum()
+    } else {
+        quotient
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::User;
+
+    fn bank_with(schedule: InterestSchedule, balance: i64, rate: u64) -> Bank {
+        let mut bank = Bank::new("Savings Bank".to_string(), rate, rate);
+        bank.interest_schedule = Some(schedule);
+        let user = User::new("Alice".to_string(), Money::MAX, balance.into());
+        bank.add_user(user).unwrap();
+        bank
+    }
+
+    fn accrue_year(bank: &mut Bank) {
+        let periods = bank.interest_schedule.unwrap().periods_per_year();
+        for _ in 0..periods {
+            bank.accrue_interest().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_small_balance_accrues_through_carry() {
+        let schedule = InterestSchedule::new(CompoundingPeriod::Daily, DayCount::Actual365);
+        let mut bank = bank_with(schedule, 10, 1000);
+        bank.accrue_interest().unwrap();
+        assert_eq!(bank.users[0].balance, Money::from_minor(10));
+        assert!(bank.users[0].interest_carry > 0);
+
+        accrue_year(&mut bank);
+        assert_eq!(bank.users[0].balance, Money::from_minor(11));
+    }
+
+    #[test]
+    fn test_year_matches_exact_compounding() {
+        // balance * (1 + 5% * days_in_year / (periods * basis))^periods,
+        // worked out to 30 digits and floored, as the carry keeps the rest
+        let cases = [
+            (
+                CompoundingPeriod::Daily,
+                DayCount::Actual365,
+                [1_051_267, -262_817, 350],
+            ),
+            (
+                CompoundingPeriod::Daily,
+                DayCount::Actual360,
+                [1_051_997, -263_000, 350],
+            ),
+            (
+                CompoundingPeriod::Monthly,
+                DayCount::Thirty360,
+                [1_051_161, -262_791, 350],
+            ),
+            (
+                CompoundingPeriod::Quarterly,
+                DayCount::Actual365,
+                [1_050_945, -262_737, 349],
+            ),
+        ];
+        for (period, day_count, expected) in cases {
+            for (balance, expected) in [1_000_000, -250_000, 333].into_iter().zip(expected) {
+                let schedule = InterestSchedule::new(period, day_count);
+                let mut bank = bank_with(schedule, balance, 500);
+                accrue_year(&mut bank);
+                assert_eq!(
+                    bank.users[0].balance,
+                    Money::from_minor(expected),
+                    "{:?}/{:?} {}",
+                    period,
+                    day_count,
+                    balance
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_scheduled_interest_replays() {
+        let schedule = InterestSchedule::new(CompoundingPeriod::Monthly, DayCount::Actual365);
+        let mut bank = bank_with(schedule, 12_345, 375);
+        accrue_year(&mut bank);
+
+        let mut rebuilt = Bank::new("Savings Bank".to_string(), 375, 375);
+        rebuilt.interest_schedule = Some(schedule);
+        rebuilt.replay(bank.ledger()).unwrap();
+        assert_eq!(rebuilt, bank);
+    }
+}
@@ -0,0 +1,450 @@
+use std::error::Error;
+use std::fmt;
+
+use super::{AccountStatus, Bank, FeeTrigger, LoanId, Money, TransferLimits, User, normalize_name};
+
+/// What caused a balance change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    /// User was added with an opening balance and credit line
+    Open { credit_line: Money },
+    /// Funds moved between two users
+    Transfer,
+    /// Interest paid to (or charged from) a user, leaving `carry` unposted
+    Interest { carry: i64 },
+    /// User was brought in from another bank by `merge_bank`, changing
+    /// their credit line by `credit_line`. Without a user on either side it
+    /// carries the other bank's fee revenue.
+    Merge { bank: String, credit_line: Money },
+    /// User was moved out to another bank by `split_off`, taking their balance
+    Split { bank: String },
+    /// Fee taken from a user into the bank's fee revenue
+    Fee { trigger: FeeTrigger },
+    /// User's credit line was changed by `set_credit_line`; moves no money
+    CreditLine { credit_line: Money },
+    /// Payment to or from `user` at another bank, cleared through the
+    /// bank's settlement account
+    Clearing { bank: String, user: String },
+    /// User's account changed state; moves no money
+    Status { status: AccountStatus },
+    /// User's transfer limits were changed; moves no money
+    Limits { limits: TransferLimits },
+    /// Closes the settlement account at the end of a clearing cycle of
+    /// `house`; `net` is what the bank received minus what it sent
+    Settlement { house: String, net: Money },
+    /// Loan paid out to a user
+    LoanDisbursement { loan: LoanId },
+    /// Interest part of a loan installment, paid by the borrower
+    LoanInterest { loan: LoanId },
+    /// Principal repaid on a loan, as an installment or early
+    LoanPrincipal { loan: LoanId },
+    /// Loan with `outstanding` principal taken over from `bank` by a merge
+    /// (positive) or handed to it by a split (negative); moves no user money
+    LoanTransfer {
+        loan: LoanId,
+        bank: String,
+        outstanding: Money,
+    },
+}
+
+/// A single immutable ledger record.
+///
+/// Money always flows from `from` to `to`; `None` on either side means the
+/// bank itself (e.g. interest or an opening balance).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerEntry {
+    pub seq: u64,
+    pub kind: EntryKind,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub amount: Money,
+    pub from_balance: Option<Money>, // balance of `from` after the entry
+    pub to_balance: Option<Money>,   // balance of `to` after the entry
+}
+
+impl LedgerEntry {
+    /// True if the entry moved money in or out of the named user
+    pub fn involves(&self, name: &str) -> bool {
+        self.from.as_deref() == Some(name) || self.to.as_deref() == Some(name)
+    }
+}
+
+/// Append-only list of every balance change made to a bank.
+///
+/// Each entry is also a balanced double-entry posting between two accounts,
+/// see [`LedgerEntry::posting`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ledger {
+    entries: Vec<LedgerEntry>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    NotEmpty,
+    UserNotFound { seq: u64, name: String },
+    DuplicateUser { seq: u64, name: String },
+    BalanceMismatch { seq: u64, name: String },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::NotEmpty => write!(f, "Ledger can only be replayed onto an empty bank"),
+            ReplayError::UserNotFound { seq, name } => {
+                write!(f, "Entry {} refers to unknown user {}", seq, name)
+            }
+            ReplayError::DuplicateUser { seq, name } => {
+                write!(f, "Entry {} opens existing user {}", seq, name)
+            }
+            ReplayError::BalanceMismatch { seq, name } => {
+                write!(
+                    f,
+                    "Entry {} does not reproduce the balance of {}",
+                    seq, name
+                )
+            }
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+impl Ledger {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, LedgerEntry> {
+        self.entries.iter()
+    }
+
+    /// Entries that moved money in or out of the user now holding `name`,
+    /// oldest first.
+    ///
+    /// Names match ignoring case and surrounding whitespace. A name freed by
+    /// a split can be taken by a new customer, so entries before the last
+    /// `Open` or `Split` of the name belong to an earlier holder and are
+    /// skipped.
+    pub fn for_user<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a LedgerEntry> {
+        let key = normalize_name(name);
+        let involves = move |e: &LedgerEntry| {
+            [&e.from, &e.to]
+                .into_iter()
+                .flatten()
+                .any(|n| normalize_name(n) == key)
+        };
+        let start = self
+            .entries
+            .iter()
+            .rposition(|e| {
+                involves(e) && matches!(e.kind, EntryKind::Open { .. } | EntryKind::Split { .. })
+            })
+            .map_or(0, |idx| match self.entries[idx].kind {
+                EntryKind::Split { .. } => idx + 1,
+                _ => idx,
+            });
+        self.entries[start..].iter().filter(move |e| involves(e))
+    }
+
+    pub(crate) fn from_entries(entries: Vec<LedgerEntry>) -> Self {
+        Self { entries }
+    }
+
+    /// Drops entries past `len`; only used to undo an uncommitted batch
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.entries.truncate(len);
+    }
+
+    pub(crate) fn push(
+        &mut self,
+        kind: EntryKind,
+        from: Option<&User>,
+        to: Option<&User>,
+        amount: Money,
+    ) {
+        self.entries.push(LedgerEntry {
+            seq: self.entries.len() as u64,
+            kind,
+            from: from.map(|u| u.name.clone()),
+            to: to.map(|u| u.name.clone()),
+            amount,
+            from_balance: from.map(|u| u.balance),
+            to_balance: to.map(|u| u.balance),
+        });
+    }
+}
+
+impl<'a> IntoIterator for &'a Ledger {
+    type Item = &'a LedgerEntry;
+    type IntoIter = std::slice::Iter<'a, LedgerEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl Bank {
+    /// Rebuilds state by re-applying every entry of `ledger` to this bank.
+    ///
+    /// The bank must be empty. Each entry's recorded balances are checked, so
+    /// a successful replay yields the same users and ledger as the original.
+    ///
+    /// Loans, standing orders and the clock are not part of the ledger and
+    /// are not rebuilt: loan entries only move balances, so the replayed
+    /// bank has no loans outstanding. [`Bank::save`] stores them separately.
+    /// Transfer limits are rebuilt but not the daily usage counted against
+    /// them, which depends on the clock.
+    pub fn replay(&mut self, ledger: &Ledger) -> Result<(), ReplayError> {
+        if !self.users.is_empty() || !self.ledger.is_empty() {
+            return Err(ReplayError::NotEmpty);
+        }
+
+        for entry in ledger {
+            let (from_idx, to_idx) = match &entry.kind {
+                EntryKind::Open { credit_line } => {
+                    let name = entry.from.as_ref().or(entry.to.as_ref());
+                    let name = name.cloned().unwrap_or_default();
+                    if self.position(&name).is_some() {
+                        return Err(ReplayError::DuplicateUser {
+                            seq: entry.seq,
+                            name,
+                        });
+                    }
+                    let idx = self.insert_user(User::new(name, *credit_line, Money::ZERO));
+                    self.sides(entry, idx)
+                }
+                EntryKind::Merge { .. } if entry.from.is_none() && entry.to.is_none() => {
+                    self.fee_revenue = self.fee_revenue.checked_add(entry.amount).ok_or(
+                        ReplayError::BalanceMismatch {
+                            seq: entry.seq,
+                            name: self.name.clone(),
+                        },
+                    )?;
+                    (None, None)
+                }
+                EntryKind::Merge { credit_line, .. } => {
+                    let name = entry.from.as_ref().or(entry.to.as_ref());
+                    let name = name.cloned().unwrap_or_default();
+                    let idx = match self.position(&name) {
+                        Some(idx) => idx,
+                        None => self.insert_user(User::new(name, Money::ZERO, Money::ZERO)),
+                    };
+                    let user = &mut self.users[idx];
+                    user.credit_line =
+                        user.credit_line.checked_add(*credit_line).ok_or_else(|| {
+                            ReplayError::BalanceMismatch {
+                                seq: entry.seq,
+                                name: user.name.clone(),
+                            }
+                        })?;
+                    self.sides(entry, idx)
+                }
+                EntryKind::CreditLine { credit_line } => {
+                    let idx = self.find(entry, &entry.to)?;
+                    if let Some(idx) = idx {
+                        self.users[idx].credit_line = *credit_line;
+                    }
+                    (None, idx)
+                }
+                EntryKind::Status { status } => {
+                    let idx = self.find(entry, &entry.to)?;
+                    if let Some(idx) = idx {
+                        self.users[idx].status = *status;
+                        if *status == AccountStatus::Closed {
+                            self.users[idx].interest_carry = 0;
+                        }
+                    }
+                    (None, idx)
+                }
+                EntryKind::Limits { limits } => {
+                    let idx = self.find(entry, &entry.to)?;
+                    if let Some(idx) = idx {
+                        self.users[idx].limits = *limits;
+                    }
+                    (None, idx)
+                }
+                EntryKind::Settlement { .. } | EntryKind::LoanTransfer { .. } => (None, None),
+                EntryKind::Transfer
+                | EntryKind::Clearing { .. }
+                | EntryKind::LoanDisbursement { .. }
+                | EntryKind::LoanInterest { .. }
+                | EntryKind::LoanPrincipal { .. }
+                | EntryKind::Interest { .. }
+                | EntryKind::Split { .. }
+                | EntryKind::Fee { .. } => {
+                    (self.find(entry, &entry.from)?, self.find(entry, &entry.to)?)
+                }
+            };
+
+            let moves = [
+                (from_idx, entry.from_balance, true),
+                (to_idx, entry.to_balance, false),
+            ];
+            for (idx, recorded, outgoing) in moves {
+                let Some(idx) = idx else { continue };
+                let user = &mut self.users[idx];
+                let balance = if outgoing {
+                    user.balance.checked_sub(entry.amount)
+                } else {
+                    user.balance.checked_add(entry.amount)
+                };
+                match balance {
+                    Some(balance) if Some(balance) == recorded => user.balance = balance,
+                    _ => {
+                        return Err(ReplayError::BalanceMismatch {
+                            seq: entry.seq,
+                            name: user.name.clone(),
+                        });
+                    }
+                }
+            }
+
+            if let EntryKind::Interest { carry } = entry.kind
+                && let Some(idx) = from_idx.or(to_idx)
+            {
+                self.users[idx].interest_carry = carry;
+            }
+            if let EntryKind::Fee { .. } = entry.kind {
+                self.fee_revenue = self.fee_revenue.checked_add(entry.amount).ok_or(
+                    ReplayError::BalanceMismatch {
+                        seq: entry.seq,
+                        name: self.name.clone(),
+                    },
+                )?;
+            }
+
+            self.record(entry.kind.clone(), from_idx, to_idx, entry.amount);
+
+            if let EntryKind::Split { .. } = entry.kind
+                && let Some(idx) = from_idx.or(to_idx)
+            {
+                self.users.remove(idx);
+                self.reindex();
+            }
+        }
+
+        Ok(())
+    }
+
+    fn sides(&self, entry: &LedgerEntry, idx: usize) -> (Option<usize>, Option<usize>) {
+        if entry.from.is_some() {
+            (Some(idx), None)
+        } else {
+            (None, Some(idx))
+        }
+    }
+
+    fn find(
+        &self,
+        entry: &LedgerEntry,
+        name: &Option<String>,
+    ) -> Result<Option<usize>, ReplayError> {
+        match name {
+            None => Ok(None),
+            Some(name) => self
+                .position(name)
+                .map(Some)
+                .ok_or_else(|| ReplayError::UserNotFound {
+                    seq: entry.seq,
+                    name: name.clone(),
+                }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_bank() -> Bank {
+        let mut bank = Bank::new("Audit Bank".to_string(), 500, 1000);
+        bank.add_user(User::new("Alice".to_string(), 5000.into(), 2000.into()))
+            .unwrap();
+        bank.add_user(User::new("Bob".to_string(), 3000.into(), (-500).into()))
+            .unwrap();
+        bank.transfer_funds("Alice", "Bob", 500.into()).unwrap();
+        bank.accrue_interest().unwrap();
+
+        let mut other = Bank::new("Other Bank".to_string(), 600, 900);
+        other
+            .add_user(User::new("Alice".to_string(), 4000.into(), 1000.into()))
+            .unwrap();
+        other
+            .add_user(User::new(
+                "Charlie".to_string(),
+                2000.into(),
+                (-1500).into(),
+            ))
+            .unwrap();
+        bank.merge_bank(other).unwrap();
+        bank
+    }
+
+    #[test]
+    fn test_every_change_is_recorded() {
+        let bank = sample_bank();
+        let kinds: Vec<_> = bank.ledger().iter().map(|e| e.kind.clone()).collect();
+
+        assert_eq!(
+            kinds[0],
+            EntryKind::Open {
+                credit_line: Money::from_minor(5000)
+            }
+        );
+        assert_eq!(kinds[2], EntryKind::Transfer);
+        assert_eq!(kinds[3], EntryKind::Interest { carry: 0 });
+        // Bob is at exactly zero after the transfer, so only Alice earns interest
+        assert_eq!(bank.ledger().len(), 6);
+
+        let seqs: Vec<_> = bank.ledger().iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_entries_carry_resulting_balances() {
+        let bank = sample_bank();
+        let transfer = &bank.ledger().iter().nth(2).unwrap();
+        assert_eq!(transfer.from.as_deref(), Some("Alice"));
+        assert_eq!(transfer.to.as_deref(), Some("Bob"));
+        assert_eq!(transfer.amount, Money::from_minor(500));
+        assert_eq!(transfer.from_balance, Some(Money::from_minor(1500)));
+        assert_eq!(transfer.to_balance, Some(Money::ZERO));
+
+        let charlie = bank.ledger().for_user("Charlie").next().unwrap();
+        assert_eq!(charlie.from.as_deref(), Some("Charlie"));
+        assert_eq!(charlie.to, None);
+        assert_eq!(charlie.from_balance, Some(Money::from_minor(-1500)));
+    }
+
+    #[test]
+    fn test_for_user_filters_entries() {
+        let bank = sample_bank();
+        let bob: Vec<_> = bank.ledger().for_user("Bob").map(|e| e.seq).collect();
+        assert_eq!(bob, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_replay_rebuilds_identical_bank() {
+        let bank = sample_bank();
+        let mut rebuilt = Bank::new("Audit Bank".to_string(), 500, 1000);
+        rebuilt.replay(bank.ledger()).unwrap();
+        assert_eq!(rebuilt, bank);
+    }
+
+    #[test]
+    fn test_replay_requires_empty_bank() {
+        let bank = sample_bank();
+        let mut target = Bank::new("Target".to_string(), 0, 0);
+        target
+            .add_user(User::new("Dave".to_string(), 0.into(), 0.into()))
+            .unwrap();
+        assert!(matches!(
+            target.replay(bank.ledger()),
+            Err(ReplayError::NotEmpty)
+        ));
+    }
+}
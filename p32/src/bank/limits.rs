@@ -0,0 +1,312 @@
+use std::fmt;
+
+use super::{AccountError, AccountStatus, Bank, EntryKind, Money, TransferError, User};
+
+/// Velocity limits on money sent from an account; `None` means unlimited.
+///
+/// Daily limits count transfers made since the limit was set, per day of
+/// the bank's logical clock, and start afresh when the clock moves on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TransferLimits {
+    /// Largest single transfer
+    pub per_transfer: Option<Money>,
+    /// Largest total sent in one day
+    pub daily_total: Option<Money>,
+    /// Most transfers sent in one day
+    pub daily_count: Option<u32>,
+}
+
+impl TransferLimits {
+    /// True if a daily limit is set, so usage has to be counted
+    pub fn is_daily(&self) -> bool {
+        self.daily_total.is_some() || self.daily_count.is_some()
+    }
+}
+
+/// What an account has sent on one day of the bank's clock
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DailyUsage {
+    pub day: u64,
+    pub total: Money,
+    pub count: u32,
+}
+
+impl DailyUsage {
+    /// The usage as counted by a bank whose clock reads `today`, for an
+    /// account moved from one whose clock reads `their_today`: what was
+    /// sent on their current day counts for today, anything older is dropped
+    pub(super) fn moved(self, their_today: u64, today: u64) -> Self {
+        if self.day == their_today {
+            DailyUsage { day: today, ..self }
+        } else {
+            DailyUsage::default()
+        }
+    }
+}
+
+/// The limit a transfer would break, with the allowance left under it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    /// Largest single transfer allowed
+    PerTransfer(Money),
+    /// Amount that can still be sent today
+    DailyTotal(Money),
+    /// Number of transfers that can still be sent today
+    DailyCount(u32),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::PerTransfer(max) => write!(f, "at most {} per transfer", max),
+            Limit::DailyTotal(left) => write!(f, "{} left to send today", left),
+            Limit::DailyCount(left) => write!(f, "{} transfers left today", left),
+        }
+    }
+}
+
+impl User {
+    pub fn transfer_limits(&self) -> TransferLimits {
+        self.limits
+    }
+
+    /// What the user has sent on `today`; zero if that is not the day of
+    /// their last counted transfer
+    pub fn usage_on(&self, today: u64) -> DailyUsage {
+        if self.usage.day == today {
+            self.usage
+        } else {
+            DailyUsage {
+                day: today,
+                ..DailyUsage::default()
+            }
+        }
+    }
+}
+
+impl Bank {
+    /// Replaces a user's transfer limits.
+    ///
+    /// Fails if the account is closed or a limit is negative. Today's usage
+    /// so far is kept.
+    pub fn set_transfer_limits(
+        &mut self,
+        name: &str,
+        limits: TransferLimits,
+    ) -> Result<(), AccountError> {
+        let idx = self
+            .position(name)
+            .ok_or_else(|| AccountError::UserNotFound(name.to_string()))?;
+        let user = &mut self.users[idx];
+        if user.status == AccountStatus::Closed {
+            return Err(AccountError::AccountClosed(user.name.clone()));
+        }
+        for amount in [limits.per_transfer, limits.daily_total]
+            .into_iter()
+            .flatten()
+        {
+            if amount.is_negative() {
+                return Err(AccountError::NegativeLimit(amount));
+            }
+        }
+        self.set_limits(idx, limits);
+        Ok(())
+    }
+
+    /// Sets and records the limits of user `idx`, without any checks
+    pub(super) fn set_limits(&mut self, idx: usize, limits: TransferLimits) {
+        self.users[idx].limits = limits;
+        self.record(EntryKind::Limits { limits }, None, Some(idx), Money::ZERO);
+    }
+}
+
+/// Fails if sending `amount` from `user` on `today` would break one of
+/// their limits
+pub(super) fn check(user: &User, amount: Money, today: u64) -> Result<(), TransferError> {
+    let exceeded = |limit| {
+        Err(TransferError::LimitExceeded {
+            name: user.name.clone(),
+            limit,
+        })
+    };
+    let limits = user.limits;
+    let usage = user.usage_on(today);
+
+    if let Some(max) = limits.per_transfer
+        && amount > max
+    {
+        return exceeded(Limit::PerTransfer(max));
+    }
+    if let Some(max) = limits.daily_count
+        && usage.count >= max
+    {
+        return exceeded(Limit::DailyCount(max.saturating_sub(usage.count)));
+    }
+    if let Some(max) = limits.daily_total {
+        let left = max
+            .checked_sub(usage.total)
+            .map_or(Money::ZERO, |l| l.max(Money::ZERO));
+        if amount > left {
+            return exceeded(Limit::DailyTotal(left));
+        }
+    }
+    Ok(())
+}
+
+/// Counts a transfer of `amount` sent on `today` against the daily limits
+pub(super) fn record(user: &mut User, amount: Money, today: u64) {
+    if !user.limits.is_daily() {
+        return;
+    }
+    let mut usage = user.usage_on(today);
+    usage.total = usage.total.saturating_add(amount);
+    usage.count = usage.count.saturating_add(1);
+    user.usage = usage;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::testing;
+
+    /// Alice with 100.00 and `limits`, and Bob with nothing
+    fn limited_bank(limits: TransferLimits) -> Bank {
+        let users = [("Alice", 0, 10_000), ("Bob", 0, 0)];
+        let mut bank = testing::bank("Careful Bank", (0, 0), &users);
+        bank.set_transfer_limits("alice", limits).unwrap();
+        bank
+    }
+
+    fn limit_of(result: Result<(), TransferError>) -> Limit {
+        match result {
+            Err(TransferError::LimitExceeded { name, limit }) => {
+                assert_eq!(name, "Alice");
+                limit
+            }
+            other => panic!("expected a limit error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_per_transfer_limit() {
+        let limits = TransferLimits {
+            per_transfer: Some(100.into()),
+            ..TransferLimits::default()
+        };
+        let mut bank = limited_bank(limits);
+        bank.transfer_funds("Alice", "Bob", 100.into()).unwrap();
+        assert_eq!(
+            limit_of(bank.transfer_funds("Alice", "Bob", 101.into())),
+            Limit::PerTransfer(100.into())
+        );
+        // Without a daily limit nothing is counted
+        assert_eq!(bank.user("Alice").unwrap().usage, DailyUsage::default());
+        // Invalid amounts are still reported as such
+        assert!(matches!(
+            bank.transfer_funds("Alice", "Bob", Money::ZERO),
+            Err(TransferError::ZeroAmount)
+        ));
+    }
+
+    #[test]
+    fn test_daily_limits_reset_with_the_clock() {
+        let limits = TransferLimits {
+            daily_total: Some(500.into()),
+            daily_count: Some(3),
+            ..TransferLimits::default()
+        };
+        let mut bank = limited_bank(limits);
+        bank.transfer_funds("Alice", "Bob", 200.into()).unwrap();
+        bank.transfer_funds("Alice", "Bob", 250.into()).unwrap();
+        assert_eq!(
+            limit_of(bank.transfer_funds("Alice", "Bob", 51.into())),
+            Limit::DailyTotal(50.into())
+        );
+        bank.transfer_funds("Alice", "Bob", 10.into()).unwrap();
+        assert_eq!(
+            limit_of(bank.transfer_funds("Alice", "Bob", 1.into())),
+            Limit::DailyCount(0)
+        );
+        let usage = bank.user("Alice").unwrap().usage_on(0);
+        assert_eq!((usage.total, usage.count), (460.into(), 3));
+
+        bank.advance_clock(1);
+        assert_eq!(bank.user("Alice").unwrap().usage_on(1).count, 0);
+        bank.transfer_funds("Alice", "Bob", 500.into()).unwrap();
+        assert_eq!(
+            limit_of(bank.transfer_funds("Alice", "Bob", 1.into())),
+            Limit::DailyTotal(Money::ZERO)
+        );
+        assert_eq!(bank.user("Bob").unwrap().balance(), 960.into());
+    }
+
+    #[test]
+    fn test_usage_moves_with_split_and_merge() {
+        let mut bank = limited_bank(TransferLimits {
+            daily_count: Some(2),
+            ..TransferLimits::default()
+        });
+        bank.advance_clock(5);
+        bank.transfer_funds("Alice", "Bob", 1.into()).unwrap();
+
+        // Today's transfer is counted on the new bank's first day
+        let mut spun = bank
+            .split_users("Spun".to_string(), 0, 0, &["Alice", "Bob"])
+            .unwrap();
+        let alice = spun.user("Alice").unwrap();
+        assert_eq!(alice.transfer_limits().daily_count, Some(2));
+        assert_eq!(alice.usage_on(0).count, 1);
+        spun.transfer_funds("Alice", "Bob", 1.into()).unwrap();
+
+        // ...and back here on our day 5
+        bank.merge_bank(spun).unwrap();
+        assert_eq!(
+            limit_of(bank.transfer_funds("Alice", "Bob", 1.into())),
+            Limit::DailyCount(0)
+        );
+    }
+
+    #[test]
+    fn test_limit_changes_replay() {
+        let limits = TransferLimits {
+            per_transfer: Some(1000.into()),
+            ..TransferLimits::default()
+        };
+        let mut bank = limited_bank(limits);
+        bank.transfer_funds("Alice", "Bob", 1000.into()).unwrap();
+        testing::assert_replays(&bank);
+
+        let spun = bank
+            .split_users("Spun".to_string(), 0, 0, &["Alice"])
+            .unwrap();
+        assert_eq!(spun.user("Alice").unwrap().transfer_limits(), limits);
+        testing::assert_replays(&spun);
+        testing::assert_replays(&bank);
+
+        bank.merge_bank(spun).unwrap();
+        assert_eq!(bank.user("Alice").unwrap().transfer_limits(), limits);
+        testing::assert_replays(&bank);
+    }
+
+    #[test]
+    fn test_set_transfer_limits_validates() {
+        let mut bank = limited_bank(TransferLimits::default());
+        let negative = TransferLimits {
+            daily_total: Some(Money::from_minor(-1)),
+            ..TransferLimits::default()
+        };
+        assert!(matches!(
+            bank.set_transfer_limits("Alice", negative),
+            Err(AccountError::NegativeLimit(_))
+        ));
+        assert!(matches!(
+            bank.set_transfer_limits("Nobody", TransferLimits::default()),
+            Err(AccountError::UserNotFound(_))
+        ));
+        bank.close_account("Bob").unwrap();
+        assert!(matches!(
+            bank.set_transfer_limits("Bob", TransferLimits::default()),
+            Err(AccountError::AccountClosed(_))
+        ));
+    }
+}
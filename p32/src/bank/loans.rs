@@ -0,0 +1,718 @@
+use std::error::Error;
+use std::fmt;
+
+use super::{Bank, EntryKind, Money, TransferError};
+
+/// Identifier of a loan within one bank
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LoanId(pub u64);
+
+impl fmt::Display for LoanId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentFrequency {
+    Weekly,
+    Monthly,
+    Quarterly,
+}
+
+impl PaymentFrequency {
+    /// Days of the bank clock between installments
+    pub fn days(self) -> u64 {
+        match self {
+            PaymentFrequency::Weekly => 7,
+            PaymentFrequency::Monthly => 30,
+            PaymentFrequency::Quarterly => 91,
+        }
+    }
+
+    pub fn per_year(self) -> u64 {
+        match self {
+            PaymentFrequency::Weekly => 52,
+            PaymentFrequency::Monthly => 12,
+            PaymentFrequency::Quarterly => 4,
+        }
+    }
+}
+
+impl fmt::Display for PaymentFrequency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PaymentFrequency::Weekly => write!(f, "weekly"),
+            PaymentFrequency::Monthly => write!(f, "monthly"),
+            PaymentFrequency::Quarterly => write!(f, "quarterly"),
+        }
+    }
+}
+
+/// What a loan product lends and on which terms
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoanTerms {
+    pub principal: Money,
+    pub annual_rate: u64, // in basis points, compounded per installment
+    pub term: u32,        // number of installments
+    pub frequency: PaymentFrequency,
+}
+
+impl LoanTerms {
+    /// Most installments a loan may have, e.g. 100 years paid monthly
+    pub const MAX_TERM: u32 = 1200;
+
+    pub fn new(principal: Money, annual_rate: u64, term: u32, frequency: PaymentFrequency) -> Self {
+        Self {
+            principal,
+            annual_rate,
+            term,
+            frequency,
+        }
+    }
+
+    /// Amortization schedule of level installments, the first due one
+    /// period after day `start`.
+    ///
+    /// Payments are rounded up to a whole minor unit and the last installment
+    /// repays whatever principal is left. `None` if the principal or term is
+    /// zero, the term is above [`LoanTerms::MAX_TERM`] or the schedule would
+    /// overflow.
+    pub fn schedule(&self, start: u64) -> Option<Vec<Installment>> {
+        if self.term > Self::MAX_TERM {
+            return None;
+        }
+        let dues = (1..=self.term as u64)
+            .map(|n| start.checked_add(self.frequency.days().checked_mul(n)?))
+            .collect::<Option<Vec<_>>>()?;
+        amortize(self.principal, self.annual_rate, self.frequency, &dues)
+    }
+}
+
+/// One scheduled payment of a loan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Installment {
+    pub due: u64, // day of the bank clock
+    pub interest: Money,
+    pub principal: Money,
+    pub paid: bool,
+}
+
+impl Installment {
+    pub fn payment(&self) -> Money {
+        self.interest.saturating_add(self.principal)
+    }
+}
+
+/// A loan paid out to a user and repaid in installments.
+///
+/// Due installments are collected from the borrower's account as the bank's
+/// clock advances; those that cannot be paid stay overdue (in arrears) and
+/// are retried every day. No penalty interest is charged on arrears.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loan {
+    pub borrower: String,
+    pub terms: LoanTerms,
+    pub start: u64,         // day the loan was paid out
+    pub outstanding: Money, // principal not yet repaid
+    pub installments: Vec<Installment>,
+    pub(super) id: LoanId, // assigned by the bank in open_loan
+}
+
+impl Loan {
+    pub fn id(&self) -> LoanId {
+        self.id
+    }
+
+    pub fn is_repaid(&self) -> bool {
+        self.outstanding.is_zero()
+    }
+
+    /// Unpaid installments due on or before `day`
+    pub fn overdue(&self, day: u64) -> impl Iterator<Item = &Installment> {
+        self.installments
+            .iter()
+            .filter(move |i| !i.paid && i.due <= day)
+    }
+
+    /// Total of the installments overdue on `day`
+    pub fn arrears(&self, day: u64) -> Money {
+        self.overdue(day)
+            .fold(Money::ZERO, |sum, i| sum.saturating_add(i.payment()))
+    }
+
+    /// Days since the oldest overdue installment fell due, 0 if none is
+    pub fn days_past_due(&self, day: u64) -> u64 {
+        self.overdue(day).next().map_or(0, |i| day - i.due)
+    }
+
+    /// Amount that repays the loan in full on `day`: the outstanding
+    /// principal plus the interest of overdue installments
+    pub fn payoff(&self, day: u64) -> Money {
+        self.overdue(day)
+            .fold(self.outstanding, |sum, i| sum.saturating_add(i.interest))
+    }
+}
+
+#[derive(Debug)]
+pub enum LoanError {
+    UserNotFound(String),
+    LoanNotFound(LoanId),
+    /// Zero principal or term, a term above [`LoanTerms::MAX_TERM`] or a
+    /// schedule that would overflow
+    InvalidTerms,
+    AlreadyRepaid(LoanId),
+    /// An early repayment must at least clear the overdue installments
+    BelowArrears(Money),
+    /// The borrower's account cannot pay or receive the amount
+    Rejected(TransferError),
+}
+
+impl fmt::Display for LoanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoanError::UserNotFound(name) => write!(f, "User {} not found", name),
+            LoanError::LoanNotFound(id) => write!(f, "Loan {} not found", id),
+            LoanError::InvalidTerms => write!(f, "Loan terms do not give a valid schedule"),
+            LoanError::AlreadyRepaid(id) => write!(f, "Loan {} is already repaid", id),
+            LoanError::BelowArrears(arrears) => {
+                write!(f, "Repayment must cover the arrears of {}", arrears)
+            }
+            LoanError::Rejected(err) => write!(f, "Loan payment rejected: {}", err),
+        }
+    }
+}
+
+impl Error for LoanError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LoanError::Rejected(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<TransferError> for LoanError {
+    fn from(err: TransferError) -> Self {
+        LoanError::Rejected(err)
+    }
+}
+
+/// Fixed-point scale of the per-period rate in [`amortize`]
+const SCALE: i128 = 1_000_000_000_000_000_000;
+
+/// Level installments repaying `principal`, one due on each of `dues`
+fn amortize(
+    principal: Money,
+    annual_rate: u64,
+    frequency: PaymentFrequency,
+    dues: &[u64],
+) -> Option<Vec<Installment>> {
+    if !principal.is_positive() || dues.is_empty() {
+        return None;
+    }
+    let count = dues.len() as i128;
+    let rate = (annual_rate as i128).checked_mul(SCALE)? / (10_000 * frequency.per_year() as i128);
+    let mut remaining = principal.minor() as i128;
+
+    let payment = if rate == 0 {
+        div_ceil(remaining, count)
+    } else {
+        // principal * r / (1 - (1 + r)^-n), keeping the discount below SCALE
+        let growth = SCALE + rate;
+        let mut discount = SCALE;
+        for _ in 0..count {
+            discount = discount * SCALE / growth;
+        }
+        div_ceil(remaining.checked_mul(rate)?, SCALE - discount)
+    };
+
+    let mut installments = Vec::with_capacity(dues.len());
+    for (n, &due) in dues.iter().enumerate() {
+        let interest = (remaining.checked_mul(rate)? + SCALE / 2) / SCALE;
+        let principal = if n + 1 == dues.len() {
+            remaining
+        } else {
+            (payment - interest).clamp(0, remaining)
+        };
+        remaining -= principal;
+        // The payment must fit too, not just its parts
+        i64::try_from(interest + principal).ok()?;
+        installments.push(Installment {
+            due,
+            interest: Money::from_minor(interest as i64),
+            principal: Money::from_minor(principal as i64),
+            paid: false,
+        });
+    }
+    Some(installments)
+}
+
+fn div_ceil(a: i128, b: i128) -> i128 {
+    (a + b - 1) / b
+}
+
+impl Bank {
+    pub fn loans(&self) -> &[Loan] {
+        &self.loans
+    }
+
+    pub fn loan(&self, id: LoanId) -> Option<&Loan> {
+        self.loans.iter().find(|l| l.id == id)
+    }
+
+    /// Principal outstanding over all loans, saturating at [`Money::MAX`]
+    pub fn loans_outstanding(&self) -> Money {
+        self.loans
+            .iter()
+            .fold(Money::ZERO, |sum, l| sum.saturating_add(l.outstanding))
+    }
+
+    /// Lends `terms.principal` to `borrower`, paying it into their account
+    /// today, and returns the new loan's id
+    pub fn open_loan(&mut self, borrower: &str, terms: LoanTerms) -> Result<LoanId, LoanError> {
+        let idx = self
+            .position(borrower)
+            .ok_or_else(|| LoanError::UserNotFound(borrower.to_string()))?;
+        let user = &self.users[idx];
+        user.check_open()?;
+        let installments = terms.schedule(self.today).ok_or(LoanError::InvalidTerms)?;
+        let balance = user.balance.checked_add(terms.principal);
+        let balance = balance.ok_or(user.overflow())?;
+
+        let id = LoanId(self.next_loan_id);
+        self.next_loan_id += 1;
+        self.users[idx].balance = balance;
+        let kind = EntryKind::LoanDisbursement { loan: id };
+        self.record(kind, None, Some(idx), terms.principal);
+        self.loans.push(Loan {
+            borrower: self.users[idx].name.clone(),
+            terms,
+            start: self.today,
+            outstanding: terms.principal,
+            installments,
+            id,
+        });
+        Ok(id)
+    }
+
+    /// Repays a loan early and returns the amount taken from the borrower.
+    ///
+    /// `amount` first pays every overdue installment; the rest reduces the
+    /// outstanding principal, after which the remaining installments are
+    /// recalculated over the same due days. Anything beyond
+    /// [`Loan::payoff`] is not taken.
+    pub fn repay_loan(&mut self, id: LoanId, amount: Money) -> Result<Money, LoanError> {
+        let loan_idx = self
+            .loans
+            .iter()
+            .position(|l| l.id == id)
+            .ok_or(LoanError::LoanNotFound(id))?;
+        let loan = &self.loans[loan_idx];
+        if loan.is_repaid() {
+            return Err(LoanError::AlreadyRepaid(id));
+        }
+        let idx = self
+            .position(&loan.borrower)
+            .ok_or_else(|| LoanError::UserNotFound(loan.borrower.clone()))?;
+        let arrears = loan.arrears(self.today);
+        if amount.is_positive() && amount < arrears {
+            return Err(LoanError::BelowArrears(arrears));
+        }
+        let amount = amount.min(loan.payoff(self.today));
+        let (_, fees) = self.check_outgoing(idx, amount)?;
+
+        // Everything past the arrears repays principal not yet due
+        let principal_due = self.loans[loan_idx]
+            .overdue(self.today)
+            .fold(Money::ZERO, |sum, i| sum.saturating_add(i.principal));
+        let prepaid = amount.saturating_add(-arrears);
+        let remaining = self.loans[loan_idx]
+            .outstanding
+            .saturating_add(-principal_due);
+        let remaining = remaining.saturating_add(-prepaid);
+        let loan = &self.loans[loan_idx];
+        let dues: Vec<u64> = loan
+            .installments
+            .iter()
+            .filter(|i| !i.paid && i.due > self.today)
+            .map(|i| i.due)
+            .collect();
+        let rest = if remaining.is_zero() {
+            Vec::new()
+        } else {
+            amortize(
+                remaining,
+                loan.terms.annual_rate,
+                loan.terms.frequency,
+                &dues,
+            )
+            .ok_or(LoanError::InvalidTerms)?
+        };
+
+        // Every posting is worked out and checked before the first is made,
+        // so a failure cannot leave the repayment half applied
+        let mut postings: Vec<(Option<usize>, Money, Money)> = self.loans[loan_idx]
+            .installments
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| !i.paid && i.due <= self.today)
+            .map(|(n, i)| (Some(n), i.interest, i.principal))
+            .collect();
+        if prepaid.is_positive() {
+            postings.push((None, Money::ZERO, prepaid));
+        }
+        let total = postings
+            .iter()
+            .try_fold(Money::ZERO, |sum, &(_, interest, principal)| {
+                sum.checked_add(interest)?.checked_add(principal)
+            });
+        let principal = postings
+            .iter()
+            .try_fold(Money::ZERO, |sum, &(_, _, principal)| {
+                sum.checked_add(principal)
+            });
+        let balance = total.and_then(|total| self.users[idx].balance.checked_sub(total));
+        let outstanding = principal.and_then(|p| self.loans[loan_idx].outstanding.checked_sub(p));
+        if balance.is_none() || outstanding.is_none() {
+            return Err(self.users[idx].overflow().into());
+        }
+
+        for (installment, interest, principal) in postings {
+            let posted = self.post_repayment(loan_idx, idx, interest, principal);
+            debug_assert!(posted.is_ok(), "checked repayment failed: {:?}", posted);
+            posted?;
+            if let Some(n) = installment {
+                self.loans[loan_idx].installments[n].paid = true;
+            }
+        }
+        if prepaid.is_positive() {
+            let loan = &mut self.loans[loan_idx];
+            loan.installments.retain(|i| i.paid);
+            loan.installments.extend(rest);
+        }
+        self.settle_outgoing(idx, amount, fees);
+        Ok(amount)
+    }
+
+    /// Collects every installment due by today, oldest first, stopping at
+    /// the first one a borrower cannot pay.
+    ///
+    /// Each payment leaves the borrower's account under the same checks as a
+    /// transfer, so transfer fees and limits apply to it.
+    pub(super) fn collect_loan_installments(&mut self) {
+        for loan in 0..self.loans.len() {
+            let Some(idx) = self.position(&self.loans[loan].borrower) else {
+                continue;
+            };
+            for n in 0..self.loans[loan].installments.len() {
+                let installment = self.loans[loan].installments[n];
+                if installment.paid {
+                    continue;
+                }
+                if installment.due > self.today {
+                    break;
+                }
+                let payment = installment.payment();
+                if !payment.is_zero() {
+                    let Ok((_, fees)) = self.check_outgoing(idx, payment) else {
+                        break;
+                    };
+                    let (interest, principal) = (installment.interest, installment.principal);
+                    if self.post_repayment(loan, idx, interest, principal).is_err() {
+                        break;
+                    }
+                    self.settle_outgoing(idx, payment, fees);
+                }
+                self.loans[loan].installments[n].paid = true;
+            }
+        }
+    }
+
+    /// Takes interest and principal of a loan from user `idx` and records both
+    fn post_repayment(
+        &mut self,
+        loan: usize,
+        idx: usize,
+        interest: Money,
+        principal: Money,
+    ) -> Result<(), TransferError> {
+        let id = self.loans[loan].id;
+        let outstanding = self.loans[loan].outstanding.checked_sub(principal);
+        let outstanding = outstanding.ok_or(self.users[idx].overflow())?;
+        let parts = [
+            (EntryKind::LoanInterest { loan: id }, interest),
+            (EntryKind::LoanPrincipal { loan: id }, principal),
+        ];
+        for (kind, amount) in parts {
+            if amount.is_zero() {
+                continue;
+            }
+            let user = &mut self.users[idx];
+            user.balance = user.balance.checked_sub(amount).ok_or(user.overflow())?;
+            self.record(kind, Some(idx), None, amount);
+        }
+        self.loans[loan].outstanding = outstanding;
+        Ok(())
+    }
+
+    /// Takes over a loan from another bank whose clock reads `their_today`,
+    /// keeping the days until each installment falls due
+    pub(super) fn adopt_loan(&mut self, mut loan: Loan, bank: &str, their_today: u64) {
+        let shift = |day: u64| {
+            if day >= their_today {
+                self.today.saturating_add(day - their_today)
+            } else {
+                self.today.saturating_sub(their_today - day)
+            }
+        };
+        loan.start = shift(loan.start);
+        for installment in &mut loan.installments {
+            installment.due = shift(installment.due);
+        }
+        loan.id = LoanId(self.next_loan_id);
+        self.next_loan_id += 1;
+        if !loan.outstanding.is_zero() {
+            let kind = EntryKind::LoanTransfer {
+                loan: loan.id,
+                bank: bank.to_string(),
+                outstanding: loan.outstanding,
+            };
+            self.record(kind, None, None, loan.outstanding);
+        }
+        self.loans.push(loan);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::{
+        Account, AccountError, Fee, FeeRule, FeeTrigger, InternalAccount, Limit, MergePolicy,
+        StandingOrder, TransferLimits, testing,
+    };
+
+    fn sample_bank() -> Bank {
+        testing::bank("Loan Bank", (0, 0), &[("Alice", 0, 0), ("Bob", 0, 5000)])
+    }
+
+    fn monthly(principal: i64, rate: u64, term: u32) -> LoanTerms {
+        LoanTerms::new(principal.into(), rate, term, PaymentFrequency::Monthly)
+    }
+
+    #[test]
+    fn test_amortization_schedule() {
+        // 12% a year is 1% a month: the classic 1000.00 over 12 months
+        let schedule = monthly(100_000, 1200, 12).schedule(0).unwrap();
+        assert_eq!(schedule.len(), 12);
+        assert_eq!(schedule[0].payment(), Money::from_minor(8885));
+        assert_eq!(schedule[0].interest, Money::from_minor(1000));
+        assert_eq!(schedule[0].due, 30);
+        assert_eq!(schedule[11].due, 360);
+        let principal: i64 = schedule.iter().map(|i| i.principal.minor()).sum();
+        assert_eq!(principal, 100_000);
+        assert!(schedule[11].payment() <= schedule[0].payment());
+
+        let free = monthly(1000, 0, 3).schedule(5).unwrap();
+        let payments: Vec<_> = free.iter().map(|i| i.payment().minor()).collect();
+        assert_eq!(payments, vec![334, 334, 332]);
+
+        assert!(monthly(0, 500, 12).schedule(0).is_none());
+        assert!(monthly(1000, 500, 0).schedule(0).is_none());
+        assert!(monthly(1000, 500, 4_000_000_000).schedule(0).is_none());
+        assert_eq!(
+            monthly(1000, 0, LoanTerms::MAX_TERM)
+                .schedule(0)
+                .unwrap()
+                .len(),
+            1200
+        );
+    }
+
+    #[test]
+    fn test_installments_and_arrears() {
+        let mut bank = sample_bank();
+        let id = bank.open_loan("alice", monthly(120_000, 1200, 12)).unwrap();
+        assert_eq!(
+            bank.user("Alice").unwrap().balance(),
+            Money::from_minor(120_000)
+        );
+        let (_, assets) = bank.calc_balance();
+        assert_eq!(assets, Money::from_minor(120_000));
+
+        // Alice spends everything, so the second installment bounces
+        bank.advance_clock(30);
+        let payment = bank.loan(id).unwrap().installments[0].payment();
+        assert!(bank.loan(id).unwrap().installments[0].paid);
+        let left = bank.user("Alice").unwrap().balance();
+        bank.transfer_funds("Alice", "Bob", left).unwrap();
+        bank.advance_clock(40);
+
+        let loan = bank.loan(id).unwrap();
+        assert_eq!(loan.arrears(bank.today()), payment);
+        assert_eq!(loan.days_past_due(bank.today()), 10);
+
+        // Money arriving later clears the arrears the next day
+        bank.transfer_funds("Bob", "Alice", payment).unwrap();
+        bank.advance_clock(1);
+        assert!(bank.loan(id).unwrap().arrears(bank.today()).is_zero());
+        assert!(bank.user("Alice").unwrap().balance().is_zero());
+        assert!(bank.verify().is_empty());
+    }
+
+    #[test]
+    fn test_installments_follow_transfer_rules() {
+        let mut bank = sample_bank();
+        bank.fees = vec![FeeRule::new(FeeTrigger::Transfer, Fee::Flat(5.into()))];
+        let id = bank.open_loan("Bob", monthly(3000, 0, 3)).unwrap();
+        let limits = TransferLimits {
+            daily_count: Some(1),
+            ..TransferLimits::default()
+        };
+        bank.set_transfer_limits("Bob", limits).unwrap();
+
+        bank.advance_clock(30);
+        let bob = bank.user("Bob").unwrap();
+        assert_eq!(bob.balance(), Money::from_minor(8000 - 1000 - 5));
+        assert_eq!(bank.fee_revenue(), Money::from_minor(5));
+
+        // The installment used up today's only transfer
+        assert!(matches!(
+            bank.transfer_funds("Bob", "Alice", 1.into()),
+            Err(TransferError::LimitExceeded {
+                limit: Limit::DailyCount(0),
+                ..
+            })
+        ));
+
+        // A standing order takes the only transfer of day 60, so the
+        // installment due that day waits until the next
+        let order = StandingOrder::new("Bob", "Alice", 1.into(), 30, 60).times(1);
+        bank.add_standing_order(order).unwrap();
+        bank.advance_clock(30);
+        assert_eq!(bank.loan(id).unwrap().arrears(bank.today()), 1000.into());
+        bank.advance_clock(1);
+        assert!(bank.loan(id).unwrap().arrears(bank.today()).is_zero());
+        assert!(bank.verify().is_empty());
+
+        assert!(matches!(
+            bank.open_loan("Bob", monthly(1000, 0, 4_000_000_000)),
+            Err(LoanError::InvalidTerms)
+        ));
+    }
+
+    #[test]
+    fn test_replay_does_not_rebuild_loans() {
+        let mut bank = sample_bank();
+        bank.open_loan("Alice", monthly(12_000, 1200, 12)).unwrap();
+        bank.advance_clock(30);
+
+        let mut rebuilt = Bank::new("Loan Bank".to_string(), 0, 0);
+        rebuilt.replay(bank.ledger()).unwrap();
+        assert_eq!(rebuilt.users(), bank.users());
+        assert!(rebuilt.loans().is_empty());
+        assert_ne!(rebuilt.calc_balance().1, bank.calc_balance().1);
+    }
+
+    #[test]
+    fn test_early_repayment() {
+        let mut bank = sample_bank();
+        let id = bank.open_loan("Bob", monthly(60_000, 600, 6)).unwrap();
+        bank.advance_clock(30);
+        let before = bank.loan(id).unwrap().installments[1].payment();
+
+        // A partial repayment lowers the remaining installments
+        bank.repay_loan(id, 20_000.into()).unwrap();
+        let loan = bank.loan(id).unwrap();
+        assert_eq!(loan.installments.len(), 6);
+        assert!(loan.installments[1].payment() < before);
+        let due: i64 = loan.installments[1..]
+            .iter()
+            .map(|i| i.principal.minor())
+            .sum();
+        assert_eq!(due, loan.outstanding.minor());
+
+        let payoff = loan.payoff(bank.today());
+        assert_eq!(bank.repay_loan(id, Money::MAX).unwrap(), payoff);
+        assert!(bank.loan(id).unwrap().is_repaid());
+        assert!(matches!(
+            bank.repay_loan(id, 1.into()),
+            Err(LoanError::AlreadyRepaid(_))
+        ));
+
+        let trial = bank.trial_balance();
+        let loans = trial.line(&Account::Internal(InternalAccount::Loans));
+        assert_eq!(loans.unwrap().balance(), 0);
+        assert!(bank.verify().is_empty());
+        let (_, assets) = bank.calc_balance();
+        assert!(assets.is_zero());
+    }
+
+    #[test]
+    fn test_loans_move_with_accounts() {
+        let mut other = sample_bank();
+        other.advance_clock(10);
+        let id = other.open_loan("Alice", monthly(30_000, 1200, 3)).unwrap();
+        assert!(matches!(
+            other.close_account("Alice"),
+            Err(AccountError::BalanceNotZero(_))
+        ));
+        other.transfer_funds("Alice", "Bob", 30_000.into()).unwrap();
+        assert!(matches!(
+            other.close_account("Alice"),
+            Err(AccountError::LoanOutstanding(_))
+        ));
+
+        // Alice is renamed on the way in and keeps 30 days to her first due day
+        let mut bank = sample_bank();
+        bank.advance_clock(100);
+        bank.merge_bank_with(other, MergePolicy::RenameOnConflict)
+            .unwrap();
+        let loan = &bank.loans()[0];
+        assert_eq!(loan.id(), id);
+        assert_eq!(loan.borrower, "Alice (Loan Bank)");
+        assert_eq!(loan.installments[0].due, 130);
+        assert_eq!(bank.loans_outstanding(), Money::from_minor(30_000));
+        assert!(bank.verify().is_empty());
+
+        let before = bank.calc_balance();
+        let spun = bank
+            .split_users("Spun".to_string(), 0, 0, &["Alice (Loan Bank)"])
+            .unwrap();
+        assert!(bank.loans().is_empty());
+        assert_eq!(spun.loans_outstanding(), Money::from_minor(30_000));
+        assert_eq!(spun.loans()[0].installments[0].due, 30);
+        assert!(bank.verify().is_empty());
+        assert!(spun.verify().is_empty());
+        let (liabilities, assets) = bank.calc_balance();
+        let (moved_liabilities, moved_assets) = spun.calc_balance();
+        assert_eq!(before.0, liabilities.saturating_add(moved_liabilities));
+        assert_eq!(before.1, assets.saturating_add(moved_assets));
+    }
+
+    #[test]
+    fn test_repayment_must_clear_arrears() {
+        let mut bank = sample_bank();
+        let id = bank.open_loan("Alice", monthly(10_000, 1200, 2)).unwrap();
+        bank.transfer_funds("Alice", "Bob", 10_000.into()).unwrap();
+        bank.advance_clock(30);
+        let arrears = bank.loan(id).unwrap().arrears(bank.today());
+        assert!(arrears.is_positive());
+
+        bank.transfer_funds("Bob", "Alice", 15_000.into()).unwrap();
+        assert!(matches!(
+            bank.repay_loan(id, 100.into()),
+            Err(LoanError::BelowArrears(a)) if a == arrears
+        ));
+        assert!(matches!(
+            bank.open_loan("Nobody", monthly(1, 0, 1)),
+            Err(LoanError::UserNotFound(_))
+        ));
+        bank.repay_loan(id, Money::MAX).unwrap();
+        // Only the overdue installment carries interest
+        assert_eq!(
+            bank.user("Alice").unwrap().balance(),
+            Money::from_minor(15_000 - 10_000 - 100)
+        );
+    }
+}
@@ -0,0 +1,447 @@
+use std::fmt::Write;
+
+use super::{AccountStatus, Bank, EntryKind, Money, TransferError, TransferLimits};
+
+/// How [`Bank::merge_bank_with`] treats users and rates found in both banks.
+///
+/// Balances of same-named users are always added together, so a merge never
+/// creates or destroys money; the policy decides credit lines and rates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Add credit lines together and keep our rates
+    #[default]
+    Sum,
+    /// Keep the larger credit line and the larger of each rate
+    KeepMax,
+    /// Keep our credit lines and rates
+    KeepOurs,
+    /// Take the other bank's credit lines and rates
+    KeepTheirs,
+    /// Same-named users are different people: add theirs under a new name
+    RenameOnConflict,
+}
+
+impl MergePolicy {
+    fn credit_line(self, ours: Money, theirs: Money) -> Option<Money> {
+        match self {
+            MergePolicy::Sum => ours.checked_add(theirs),
+            MergePolicy::KeepMax => Some(ours.max(theirs)),
+            MergePolicy::KeepOurs | MergePolicy::RenameOnConflict => Some(ours),
+            MergePolicy::KeepTheirs => Some(theirs),
+        }
+    }
+
+    fn rate(self, ours: u64, theirs: u64) -> u64 {
+        match self {
+            MergePolicy::KeepMax => ours.max(theirs),
+            MergePolicy::KeepTheirs => theirs,
+            _ => ours,
+        }
+    }
+}
+
+/// A user present in both banks with different credit lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    pub name: String,
+    pub ours: Money,
+    pub theirs: Money,
+    pub resolved: Money,
+}
+
+/// What a merge did (or would do, see [`Bank::preview_merge`])
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeReport {
+    pub bank: String,
+    pub source: String,
+    pub policy: MergePolicy,
+    pub added: Vec<String>,
+    pub combined: Vec<String>,
+    pub renamed: Vec<(String, String)>, // (name in the other bank, name here)
+    pub conflicts: Vec<MergeConflict>,
+    pub rates_before: (u64, u64), // (credit_interest, debit_interest)
+    pub their_rates: (u64, u64),
+    pub rates_after: (u64, u64),
+    pub balance_before: (Money, Money), // calc_balance before the merge
+    pub balance_after: (Money, Money),
+}
+
+/// Change to an existing user, worked out before anything is applied
+struct Combine {
+    idx: usize,
+    balance: Money,
+    credit_line: Money,
+    credit_delta: Money,
+}
+
+impl Bank {
+    /// Merges another bank into this one using [`MergePolicy::Sum`]
+    pub fn merge_bank(&mut self, other: Bank) -> Result<MergeReport, TransferError> {
+        self.merge_bank_with(other, MergePolicy::Sum)
+    }
+
+    /// Merges another bank into this one, consuming the other bank.
+    ///
+    /// Fails without changing this bank if a combined balance or credit line
+    /// would overflow. Interest carried below one minor unit by the other
+    /// bank is not transferred, and our interest schedule, fee rules, clock
+    /// and standing orders are kept while the other bank's are dropped. Its
+    /// fee revenue is added to ours, and its loans are taken over with new
+    /// ids, falling due as many days from our clock as they would have from
+    /// theirs.
+    ///
+    /// Closed accounts cannot be combined with another account and fail the
+    /// merge with [`TransferError::AccountClosed`]. An account frozen in
+    /// either bank is frozen after the merge. Added users keep their transfer
+    /// limits, and what they sent on the other bank's current day counts
+    /// against them today; combined users keep our limits.
+    pub fn merge_bank_with(
+        &mut self,
+        other: Bank,
+        policy: MergePolicy,
+    ) -> Result<MergeReport, TransferError> {
+        // Work out every combined user first so a failure changes nothing
+        let mut plan = Vec::with_capacity(other.users.len());
+        for user in &other.users {
+            let idx = match self.position(&user.name) {
+                Some(idx) if policy != MergePolicy::RenameOnConflict => idx,
+                _ => {
+                    plan.push(None);
+                    continue;
+                }
+            };
+            let ours = &self.users[idx];
+            if ours.status == AccountStatus::Closed || user.status == AccountStatus::Closed {
+                return Err(TransferError::AccountClosed(ours.name.clone()));
+            }
+            let balance = ours.balance.checked_add(user.balance);
+            let credit_line = policy.credit_line(ours.credit_line, user.credit_line);
+            let credit_delta = credit_line.and_then(|c| c.checked_sub(ours.credit_line));
+            plan.push(Some(Combine {
+                idx,
+                balance: balance.ok_or(user.overflow())?,
+                credit_line: credit_line.ok_or(user.overflow())?,
+                credit_delta: credit_delta.ok_or(user.overflow())?,
+            }));
+        }
+
+        let fee_revenue = self
+            .fee_revenue
+            .checked_add(other.fee_revenue)
+            .ok_or_else(|| TransferError::ArithmeticOverflow(self.name.clone()))?;
+
+        let rates_before = (self.credit_interest, self.debit_interest);
+        let their_rates = (other.credit_interest, other.debit_interest);
+        let mut report = MergeReport {
+            bank: self.name.clone(),
+            source: other.name.clone(),
+            policy,
+            added: Vec::new(),
+            combined: Vec::new(),
+            renamed: Vec::new(),
+            conflicts: Vec::new(),
+            rates_before,
+            their_rates,
+            rates_after: rates_before,
+            balance_before: self.calc_balance(),
+            balance_after: (Money::ZERO, Money::ZERO),
+        };
+
+        for (mut user, step) in other.users.into_iter().zip(plan) {
+            let (balance, status, limits) = (user.balance, user.status, user.limits);
+            let added = step.is_none();
+            let (idx, credit_delta) = match step {
+                Some(step) => {
+                    let ours = &mut self.users[step.idx];
+                    if ours.credit_line != user.credit_line {
+                        report.conflicts.push(MergeConflict {
+                            name: ours.name.clone(),
+                            ours: ours.credit_line,
+                            theirs: user.credit_line,
+                            resolved: step.credit_line,
+                        });
+                    }
+                    ours.balance = step.balance;
+                    ours.credit_line = step.credit_line;
+                    report.combined.push(ours.name.clone());
+                    (step.idx, step.credit_delta)
+                }
+                None => {
+                    if self.position(&user.name).is_some() {
+                        let name = self.unique_name(&user.name, &other.name);
+                        report.renamed.push((user.name, name.clone()));
+                        user.name = name;
+                    }
+                    let credit_line = user.credit_line;
+                    user.interest_carry = 0;
+                    user.status = AccountStatus::Active;
+                    user.limits = TransferLimits::default();
+                    user.usage = user.usage.moved(other.today, self.today);
+                    report.added.push(user.name.clone());
+                    (self.insert_user(user), credit_line)
+                }
+            };
+
+            let kind = EntryKind::Merge {
+                bank: other.name.clone(),
+                credit_line: credit_delta,
+            };
+            self.record_deposit(kind, idx, balance);
+            // A frozen or closed account stays so, recorded after it opens here
+            if status != AccountStatus::Active && self.users[idx].status != status {
+                self.set_status(idx, status);
+            }
+            if added && limits != TransferLimits::default() {
+                self.set_limits(idx, limits);
+            }
+        }
+
+        for mut loan in other.loans {
+            let renamed = report
+                .renamed
+                .iter()
+                .find(|(from, _)| *from == loan.borrower);
+            loan.borrower = match renamed {
+                Some((_, to)) => to.clone(),
+                None => self
+                    .user(&loan.borrower)
+                    .map_or(loan.borrower, |u| u.name.clone()),
+            };
+            self.adopt_loan(loan, &other.name, other.today);
+        }
+
+        if !other.fee_revenue.is_zero() {
+            self.fee_revenue = fee_revenue;
+            let kind = EntryKind::Merge {
+                bank: other.name.clone(),
+                credit_line: Money::ZERO,
+            };
+            self.record(kind, None, None, other.fee_revenue);
+        }
+
+        self.credit_interest = policy.rate(self.credit_interest, other.credit_interest);
+        self.debit_interest = policy.rate(self.debit_interest, other.debit_interest);
+        report.rates_after = (self.credit_interest, self.debit_interest);
+        report.balance_after = self.calc_balance();
+        Ok(report)
+    }
+
+    /// Report of what [`Bank::merge_bank_with`] would do, leaving both banks as they are
+    pub fn preview_merge(
+        &self,
+        other: &Bank,
+        policy: MergePolicy,
+    ) -> Result<MergeReport, TransferError> {
+        self.clone().merge_bank_with(other.clone(), policy)
+    }
+
+    /// `name (bank)`, numbered if needed, that no user here has yet
+    fn unique_name(&self, name: &str, bank: &str) -> String {
+        let mut candidate = format!("{} ({})", name, bank);
+        let mut n = 2;
+        while self.position(&candidate).is_some() {
+            candidate = format!("{} ({} {})", name, bank, n);
+            n += 1;
+        }
+        candidate
+    }
+}
+
+impl MergeReport {
+    /// Plain-text summary for review
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "Merge of {} into {} ({:?})",
+            self.source, self.bank, self.policy
+        );
+        for name in &self.added {
+            let _ = writeln!(out, "  added     {}", name);
+        }
+        for name in &self.combined {
+            let _ = writeln!(out, "  combined  {}", name);
+        }
+        for (from, to) in &self.renamed {
+            let _ = writeln!(out, "  renamed   {} -> {}", from, to);
+        }
+        for c in &self.conflicts {
+            let _ = writeln!(
+                out,
+                "  conflict  {}: credit line {} ours, {} theirs, now {}",
+                c.name, c.ours, c.theirs, c.resolved
+            );
+        }
+        let (credit, debit) = self.rates_before;
+        let (their_credit, their_debit) = self.their_rates;
+        let (new_credit, new_debit) = self.rates_after;
+        let _ = writeln!(
+            out,
+            "Rates (bps):  {}/{} -> {}/{} (theirs {}/{})",
+            credit, debit, new_credit, new_debit, their_credit, their_debit
+        );
+        let _ = writeln!(
+            out,
+            "Liabilities:  {} -> {}",
+            self.balance_before.0, self.balance_after.0
+        );
+        let _ = writeln!(
+            out,
+            "Assets:       {} -> {}",
+            self.balance_before.1, self.balance_after.1
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::{Fee, FeeRule, FeeTrigger, User, testing};
+
+    fn banks() -> (Bank, Bank) {
+        let ours = testing::bank(
+            "Bank A",
+            (500, 1000),
+            &[("Alice", 5000, 2000), ("Bob", 3000, -500)],
+        );
+        let theirs = testing::bank(
+            "Bank B",
+            (600, 900),
+            &[("alice", 4000, 1000), ("Charlie", 2000, 1500)],
+        );
+        (ours, theirs)
+    }
+
+    fn credit_line(bank: &Bank, name: &str) -> Money {
+        bank.user(name).unwrap().credit_line
+    }
+
+    #[test]
+    fn test_credit_line_policies() {
+        let cases = [
+            (MergePolicy::Sum, 9000, (500, 1000)),
+            (MergePolicy::KeepMax, 5000, (600, 1000)),
+            (MergePolicy::KeepOurs, 5000, (500, 1000)),
+            (MergePolicy::KeepTheirs, 4000, (600, 900)),
+        ];
+        for (policy, expected, rates) in cases {
+            let (mut ours, theirs) = banks();
+            let report = ours.merge_bank_with(theirs, policy).unwrap();
+
+            let alice = ours.user("Alice").unwrap();
+            assert_eq!(alice.balance, Money::from_minor(3000), "{:?}", policy);
+            assert_eq!(
+                alice.credit_line,
+                Money::from_minor(expected),
+                "{:?}",
+                policy
+            );
+            assert_eq!((ours.credit_interest, ours.debit_interest), rates);
+
+            assert_eq!(report.added, vec!["Charlie"]);
+            assert_eq!(report.combined, vec!["Alice"]);
+            assert_eq!(report.conflicts.len(), 1);
+            assert_eq!(report.conflicts[0].resolved, Money::from_minor(expected));
+        }
+    }
+
+    #[test]
+    fn test_rename_on_conflict() {
+        let (mut ours, theirs) = banks();
+        let report = ours
+            .merge_bank_with(theirs, MergePolicy::RenameOnConflict)
+            .unwrap();
+
+        assert_eq!(ours.users().len(), 4);
+        assert_eq!(
+            report.renamed,
+            vec![("alice".to_string(), "alice (Bank B)".to_string())]
+        );
+        assert!(report.combined.is_empty());
+        assert_eq!(ours.user("Alice").unwrap().balance, Money::from_minor(2000));
+        assert_eq!(
+            credit_line(&ours, "Alice (bank b)"),
+            Money::from_minor(4000)
+        );
+
+        // Renamed users still replay to the same bank
+        let mut rebuilt = Bank::new("Bank A".to_string(), 500, 1000);
+        rebuilt.replay(ours.ledger()).unwrap();
+        assert_eq!(rebuilt, ours);
+    }
+
+    #[test]
+    fn test_report_balances_and_preview() {
+        let (mut ours, theirs) = banks();
+        let preview = ours
+            .preview_merge(&theirs, MergePolicy::KeepTheirs)
+            .unwrap();
+        assert_eq!(ours.users().len(), 2);
+        assert_eq!(ours.debit_interest, 1000);
+
+        assert_eq!(
+            preview.balance_before,
+            (Money::from_minor(2000), Money::from_minor(500))
+        );
+        assert_eq!(
+            preview.balance_after,
+            (Money::from_minor(4500), Money::from_minor(500))
+        );
+
+        let report = ours
+            .merge_bank_with(theirs, MergePolicy::KeepTheirs)
+            .unwrap();
+        assert_eq!(report, preview);
+        assert!(
+            report
+                .to_text()
+                .contains("conflict  Alice: credit line 50.00 ours, 40.00 theirs, now 40.00")
+        );
+
+        // Lowered credit lines are recorded as a negative change and replay
+        let mut rebuilt = Bank::new("Bank A".to_string(), 500, 1000);
+        rebuilt.replay(ours.ledger()).unwrap();
+        assert_eq!(credit_line(&rebuilt, "Alice"), Money::from_minor(4000));
+    }
+
+    #[test]
+    fn test_merge_keeps_fee_revenue() {
+        let (mut ours, mut theirs) = banks();
+        theirs.fees = vec![FeeRule::new(FeeTrigger::Transfer, Fee::Flat(7.into()))];
+        theirs
+            .transfer_funds("Charlie", "alice", 100.into())
+            .unwrap();
+        let (liabilities, _) = theirs.calc_balance();
+
+        let report = ours.merge_bank(theirs).unwrap();
+        assert_eq!(ours.fee_revenue(), Money::from_minor(7));
+        assert_eq!(
+            report.balance_after.0.minor(),
+            report.balance_before.0.minor() + liabilities.minor()
+        );
+
+        let mut rebuilt = Bank::new("Bank A".to_string(), 500, 1000);
+        rebuilt.replay(ours.ledger()).unwrap();
+        assert_eq!(rebuilt, ours);
+    }
+
+    #[test]
+    fn test_merge_overflow_changes_nothing() {
+        let (mut ours, _) = banks();
+        let mut theirs = Bank::new("Bank B".to_string(), 0, 0);
+        theirs
+            .add_user(User::new("Zed".to_string(), 0.into(), 10.into()))
+            .unwrap();
+        theirs
+            .add_user(User::new("Alice".to_string(), 0.into(), Money::MAX))
+            .unwrap();
+
+        let before = ours.clone();
+        assert!(matches!(
+            ours.merge_bank_with(theirs, MergePolicy::KeepOurs),
+            Err(TransferError::ArithmeticOverflow(_))
+        ));
+        assert_eq!(ours, before);
+    }
+}
@@ -0,0 +1,215 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::Neg;
+use std::str::FromStr;
+
+/// Minor units (cents) per major unit
+pub const MINOR_PER_MAJOR: i64 = 100;
+
+/// An amount of money in minor units.
+///
+/// The range is symmetric (`-i64::MAX..=i64::MAX`) so negation and `abs`
+/// can never overflow. All arithmetic is checked and returns `None` instead
+/// of wrapping.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Money(i64);
+
+/// How to round when a result falls between two minor units
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    /// Round half away from zero
+    HalfUp,
+    /// Round half to the nearest even minor unit
+    HalfEven,
+    /// Truncate toward zero
+    Down,
+}
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+    pub const MAX: Money = Money(i64::MAX);
+    pub const MIN: Money = Money(-i64::MAX);
+
+    /// Creates an amount from minor units, clamping `i64::MIN` to [`Money::MIN`]
+    pub const fn from_minor(minor: i64) -> Self {
+        if minor == i64::MIN {
+            Money::MIN
+        } else {
+            Money(minor)
+        }
+    }
+
+    pub const fn minor(self) -> i64 {
+        self.0
+    }
+
+    pub const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub const fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+
+    pub const fn abs(self) -> Money {
+        Money(self.0.abs())
+    }
+
+    pub fn checked_add(self, other: Money) -> Option<Money> {
+        Money::checked(self.0.checked_add(other.0)?)
+    }
+
+    pub fn checked_sub(self, other: Money) -> Option<Money> {
+        Money::checked(self.0.checked_sub(other.0)?)
+    }
+
+    pub fn checked_mul(self, factor: i64) -> Option<Money> {
+        Money::checked(self.0.checked_mul(factor)?)
+    }
+
+    pub fn saturating_add(self, other: Money) -> Money {
+        Money::from_minor(self.0.saturating_add(other.0))
+    }
+
+    /// Multiplies by `num / den`, rounding the result to a minor unit
+    pub fn checked_mul_ratio(self, num: u64, den: u64, rounding: Rounding) -> Option<Money> {
+        if den == 0 {
+            return None;
+        }
+        let value = self.0 as i128 * num as i128;
+        let den = den as i128;
+        let quotient = value / den;
+        let remainder = (value % den).abs() * 2;
+
+        let round_away = match rounding {
+            Rounding::Down => false,
+            Rounding::HalfUp => remainder >= den,
+            Rounding::HalfEven => remainder > den || (remainder == den && quotient % 2 != 0),
+        };
+        let rounded = if round_away {
+            quotient + value.signum()
+        } else {
+            quotient
+        };
+        Money::checked(i64::try_from(rounded).ok()?)
+    }
+
+    /// Multiplies by a rate in basis points (0.01%)
+    pub fn checked_mul_bps(self, bps: u64, rounding: Rounding) -> Option<Money> {
+        self.checked_mul_ratio(bps, 10_000, rounding)
+    }
+
+    fn checked(minor: i64) -> Option<Money> {
+        (minor != i64::MIN).then_some(Money(minor))
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl From<i64> for Money {
+    fn from(minor: i64) -> Self {
+        Money::from_minor(minor)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        let per = MINOR_PER_MAJOR as u64;
+        f.pad(&format!("{}{}.{:02}", sign, abs / per, abs % per))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseMoneyError(String);
+
+impl fmt::Display for ParseMoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid amount {:?}", self.0)
+    }
+}
+
+impl Error for ParseMoneyError {}
+
+impl FromStr for Money {
+    type Err = ParseMoneyError;
+
+    /// Parses major units with up to two decimals, e.g. `12`, `-0.5`, `3.25`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseMoneyError(s.to_string());
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let (major, minor) = digits.split_once('.').unwrap_or((digits, ""));
+        let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if major.is_empty() || minor.len() > 2 || !all_digits(major) || !all_digits(minor) {
+            return Err(err());
+        }
+
+        let major: i64 = major.parse().map_err(|_| err())?;
+        let minor: i64 = format!("{:0<2}", minor).parse().map_err(|_| err())?;
+        let value = major
+            .checked_mul(MINOR_PER_MAJOR)
+            .and_then(|v| v.checked_add(minor))
+            .ok_or_else(err)?;
+        Ok(Money(if negative { -value } else { value }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let a = Money::from_minor(i64::MAX - 1);
+        assert_eq!(a.checked_add(Money::from_minor(1)), Some(Money::MAX));
+        assert_eq!(a.checked_add(Money::from_minor(2)), None);
+        assert_eq!(Money::MIN.checked_sub(Money::from_minor(1)), None);
+        assert_eq!(Money::MAX.checked_mul(2), None);
+        assert_eq!(-Money::MIN, Money::MAX);
+    }
+
+    #[test]
+    fn test_rounding() {
+        let m = Money::from_minor(25);
+        // 25 * 10% = 2.5 minor units
+        assert_eq!(m.checked_mul_bps(1000, Rounding::HalfUp), Some(3.into()));
+        assert_eq!(m.checked_mul_bps(1000, Rounding::HalfEven), Some(2.into()));
+        assert_eq!(m.checked_mul_bps(1000, Rounding::Down), Some(2.into()));
+
+        let m = Money::from_minor(-35);
+        assert_eq!(m.checked_mul_bps(1000, Rounding::HalfUp), Some((-4).into()));
+        assert_eq!(
+            m.checked_mul_bps(1000, Rounding::HalfEven),
+            Some((-4).into())
+        );
+        assert_eq!(m.checked_mul_bps(1000, Rounding::Down), Some((-3).into()));
+
+        assert_eq!(Money::MAX.checked_mul_bps(20_000, Rounding::HalfUp), None);
+    }
+
+    #[test]
+    fn test_display_and_parse() {
+        assert_eq!(Money::from_minor(-105).to_string(), "-1.05");
+        assert_eq!(Money::from_minor(7).to_string(), "0.07");
+        assert_eq!("12.3".parse(), Ok(Money::from_minor(1230)));
+        assert_eq!("-0.05".parse(), Ok(Money::from_minor(-5)));
+        assert_eq!("42".parse(), Ok(Money::from_minor(4200)));
+        assert!("1.234".parse::<Money>().is_err());
+        assert!("abc".parse::<Money>().is_err());
+        assert!("92233720368547758.08".parse::<Money>().is_err());
+    }
+}
@@ -0,0 +1,948 @@
+//! On-disk format for [`Bank`].
+//!
+//! A bank file is UTF-8 text with one tab-separated record per line:
+//!
+//! ```text
+//! p32-bank    <version>
+//! bank        <name>  <credit_interest>  <debit_interest>
+//! schedule    <period>  <day_count>                  (optional)
+//! fee-rule    <fee>  <trigger>                       (zero or more)
+//! clock       <today>  <next_order_id>  <next_loan_id>
+//! order       <id>  <from>  <to>  <amount>  <every>  <start>  <remaining>
+//! loan        <id>  <borrower>  <principal>  <annual_rate>  <term>  <frequency>  <start>  <outstanding>
+//! installment <due>  <interest>  <principal>  <paid|unpaid>   (of the loan above)
+//! user        <name>  <credit_line>  <balance>  <per_transfer>  <daily_total>  <daily_count>  <day>  <sent>  <count>
+//! entry       <seq>   <from>  <to>  <amount>  <from_balance>  <to_balance>  <kind> [args]
+//! checksum    <fnv-1a 64 of every preceding byte, 16 hex digits>
+//! ```
+//!
+//! The last six user fields are the transfer limits, each optional, and the
+//! day, total and count of the user's counted transfers.
+//! Amounts are integers in minor units. Strings escape `\`, tab, newline and
+//! carriage return as `\\`, `\t`, `\n` and `\r`. Optional fields are written
+//! as `-` when absent and `+<value>` when present. Entry kinds are
+//! `open <credit_line>`, `transfer`, `interest <carry>`,
+//! `merge <bank> <credit_line>`, `split <bank>`, `fee <trigger>`,
+//! `credit-line <credit_line>`, `clearing <bank> <user>`,
+//! `settlement <house> <net>`, `status <status>`,
+//! `limits <per_transfer> <daily_total> <daily_count>`, `loan-disbursement <loan>`,
+//! `loan-interest <loan>`, `loan-principal <loan>` and
+//! `loan-transfer <loan> <bank> <outstanding>`, where a status is `active`,
+//! `frozen` or `closed`. Loan frequencies are `weekly`, `monthly` or
+//! `quarterly`.
+//! Triggers are `transfer`, `overdraft` or `maintenance <every>`. Fees are
+//! space-separated words in prefix form: `flat <amount>`, `percent <bps>`,
+//! `capped <min> <max> <fee>` and `tiered <count>` followed by `<up_to> <fee>`
+//! per tier, with `-` for no upper bound. Schedule periods are `daily`, `monthly` or
+//! `quarterly` and day counts `act365`, `act360` or `30/360`.
+//!
+//! Versions:
+//! * 1 - bank and user records only.
+//! * 2 - adds the ledger. Version 1 files are migrated by opening every
+//!   user with its stored balance, in file order.
+//! * 3 - adds the interest schedule and the interest carry of `interest`
+//!   entries. Older interest entries have no carry.
+//! * 4 - adds `split` entries.
+//! * 5 - adds the logical clock and standing orders. Older files start on
+//!   day 0 with no orders.
+//! * 6 - adds fee rules and `fee` entries.
+//! * 7 - adds `credit-line` entries.
+//! * 8 - adds `clearing` and `settlement` entries.
+//! * 9 - adds `status` entries.
+//! * 10 - adds loans, their entries and the next loan id to the clock.
+//! * 11 - adds transfer limits and daily usage to `user` records. Older
+//!   users are unlimited.
+//! * 12 - adds `limits` entries. Version 11 files take the limits of their
+//!   `user` records instead.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::{
+    AccountStatus, Bank, CompoundingPeriod, DayCount, EntryKind, Fee, FeeRule, FeeTier, FeeTrigger,
+    Installment, InterestSchedule, Ledger, LedgerEntry, Loan, LoanId, LoanTerms, Money, OrderId,
+    PaymentFrequency, StandingOrder, TransferLimits, User,
+};
+
+pub const FORMAT_VERSION: u32 = 12;
+
+const MAGIC: &str = "p32-bank";
+
+#[derive(Debug)]
+pub enum PersistError {
+    Io(io::Error),
+    BadHeader,
+    UnsupportedVersion(u32),
+    Truncated,
+    ChecksumMismatch,
+    Malformed { line: usize, reason: String },
+}
+
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PersistError::Io(err) => write!(f, "I/O error: {}", err),
+            PersistError::BadHeader => write!(f, "Not a bank file"),
+            PersistError::UnsupportedVersion(v) => write!(f, "Unsupported format version {}", v),
+            PersistError::Truncated => write!(f, "Bank file is truncated"),
+            PersistError::ChecksumMismatch => write!(f, "Bank file checksum does not match"),
+            PersistError::Malformed { line, reason } => {
+                write!(f, "Malformed bank file at line {}: {}", line, reason)
+            }
+        }
+    }
+}
+
+impl Error for PersistError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PersistError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PersistError {
+    fn from(err: io::Error) -> Self {
+        PersistError::Io(err)
+    }
+}
+
+impl Bank {
+    /// Writes the bank to `path` in the current format version.
+    ///
+    /// The file is written next to `path` first and then renamed over it, so
+    /// an interrupted save never leaves a half-written bank behind.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), PersistError> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+
+        fs::write(&tmp, self.serialize())?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Reads a bank written by [`Bank::save`], migrating older versions
+    pub fn load(path: impl AsRef<Path>) -> Result<Bank, PersistError> {
+        Bank::deserialize(&fs::read_to_string(path)?)
+    }
+
+    /// Encodes the bank in the current format version
+    pub fn serialize(&self) -> String {
+        let mut out = format!("{}\t{}\n", MAGIC, FORMAT_VERSION);
+        out += &format!(
+            "bank\t{}\t{}\t{}\n",
+            escape(&self.name),
+            self.credit_interest,
+            self.debit_interest
+        );
+        if let Some(schedule) = &self.interest_schedule {
+            out += &format!(
+                "schedule\t{}\t{}\n",
+                encode_period(schedule.period),
+                encode_day_count(schedule.day_count)
+            );
+        }
+        for rule in &self.fees {
+            out += &format!(
+                "fee-rule\t{}\t{}\n",
+                encode_fee(&rule.fee),
+                encode_trigger(rule.trigger)
+            );
+        }
+        out += &format!(
+            "clock\t{}\t{}\t{}\n",
+            self.today, self.next_order_id, self.next_loan_id
+        );
+        for order in &self.orders {
+            out += &format!(
+                "order\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                order.id.0,
+                escape(&order.from),
+                escape(&order.to),
+                order.amount.minor(),
+                order.every,
+                order.start,
+                optional(order.remaining)
+            );
+        }
+        for loan in &self.loans {
+            out += &encode_loan(loan);
+        }
+        for user in &self.users {
+            let limits = user.limits;
+            out += &format!(
+                "user\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                escape(&user.name),
+                user.credit_line.minor(),
+                user.balance.minor(),
+                optional(limits.per_transfer.map(Money::minor)),
+                optional(limits.daily_total.map(Money::minor)),
+                optional(limits.daily_count),
+                user.usage.day,
+                user.usage.total.minor(),
+                user.usage.count
+            );
+        }
+        for entry in &self.ledger {
+            out += &encode_entry(entry);
+        }
+        out += &format!("checksum\t{:016x}\n", checksum(out.as_bytes()));
+        out
+    }
+
+    /// Decodes a bank from the text produced by [`Bank::serialize`]
+    pub fn deserialize(text: &str) -> Result<Bank, PersistError> {
+        let body = verify_checksum(text)?;
+        let mut lines = body.lines().enumerate().map(|(i, l)| (i + 1, l));
+
+        let version = match lines.next() {
+            Some((_, header)) => match header.split_once('\t') {
+                Some((MAGIC, v)) => v.parse::<u32>().map_err(|_| PersistError::BadHeader)?,
+                _ => return Err(PersistError::BadHeader),
+            },
+            None => return Err(PersistError::BadHeader),
+        };
+        if version == 0 || version > FORMAT_VERSION {
+            return Err(PersistError::UnsupportedVersion(version));
+        }
+
+        let mut bank = None;
+        let mut clock = (0, 1, 1);
+        let mut orders = Vec::new();
+        let mut loans: Vec<Loan> = Vec::new();
+        let mut users = Vec::new();
+        let mut entries = Vec::new();
+        for (line, text) in lines {
+            let fields: Vec<&str> = text.split('\t').collect();
+            let r = Record {
+                line,
+                fields: &fields[1..],
+            };
+            match fields[0] {
+                "bank" if bank.is_none() => {
+                    bank = Some(Bank::new(r.string(0)?, r.num(1)?, r.num(2)?));
+                }
+                "schedule" if version >= 3 => {
+                    let bank = bank
+                        .as_mut()
+                        .ok_or_else(|| r.malformed("schedule before bank".to_string()))?;
+                    bank.interest_schedule = Some(InterestSchedule::new(
+                        decode_period(&r)?,
+                        decode_day_count(&r)?,
+                    ));
+                }
+                "fee-rule" if version >= 6 => {
+                    let bank = bank
+                        .as_mut()
+                        .ok_or_else(|| r.malformed("fee rule before bank".to_string()))?;
+                    let fee = decode_fee(&r, 0)?;
+                    bank.fees.push(FeeRule::new(decode_trigger(&r, 1)?, fee));
+                }
+                "clock" if version >= 10 => clock = (r.num(0)?, r.num(1)?, r.num(2)?),
+                "clock" if version >= 5 => clock = (r.num(0)?, r.num(1)?, 1),
+                "order" if version >= 5 => orders.push(decode_order(&r)?),
+                "loan" if version >= 10 => loans.push(decode_loan(&r)?),
+                "installment" if version >= 10 => {
+                    let loan = loans
+                        .last_mut()
+                        .ok_or_else(|| r.malformed("installment before loan".to_string()))?;
+                    loan.installments.push(decode_installment(&r)?);
+                }
+                "user" => users.push(decode_user(&r, version)?),
+                "entry" if version >= 2 => entries.push(decode_entry(&r, version)?),
+                other => return Err(r.malformed(format!("unexpected record {:?}", other))),
+            }
+        }
+        let mut bank = bank.ok_or(PersistError::Truncated)?;
+        (bank.today, bank.next_order_id, bank.next_loan_id) = clock;
+        bank.orders = orders;
+        bank.loans = loans;
+
+        if version < 2 {
+            migrate_v1(&mut bank, users)?;
+            return Ok(bank);
+        }
+
+        let ledger = Ledger::from_entries(entries);
+        bank.replay(&ledger)
+            .map_err(|err| PersistError::Malformed {
+                line: 0,
+                reason: err.to_string(),
+            })?;
+        // Limits come from the ledger since version 12
+        let same = |(a, b): (&User, &User)| {
+            a.name == b.name
+                && a.credit_line == b.credit_line
+                && a.balance == b.balance
+                && (version < 12 || a.limits == b.limits)
+        };
+        if bank.users.len() != users.len() || !bank.users.iter().zip(&users).all(same) {
+            return Err(PersistError::Malformed {
+                line: 0,
+                reason: "users do not match the ledger".to_string(),
+            });
+        }
+        for (user, stored) in bank.users.iter_mut().zip(users) {
+            if version < 12 {
+                user.limits = stored.limits;
+            }
+            user.usage = stored.usage;
+        }
+        Ok(bank)
+    }
+}
+
+/// Version 1 files have no ledger: open every user with its stored balance
+fn migrate_v1(bank: &mut Bank, users: Vec<User>) -> Result<(), PersistError> {
+    for user in users {
+        bank.add_user(user).map_err(|err| PersistError::Malformed {
+            line: 0,
+            reason: err.to_string(),
+        })?;
+    }
+    Ok(())
+}
+
+fn encode_entry(entry: &LedgerEntry) -> String {
+    let kind = match &entry.kind {
+        EntryKind::Open { credit_line } => format!("open\t{}", credit_line.minor()),
+        EntryKind::Transfer => "transfer".to_string(),
+        EntryKind::Interest { carry } => format!("interest\t{}", carry),
+        EntryKind::Merge { bank, credit_line } => {
+            format!("merge\t{}\t{}", escape(bank), credit_line.minor())
+        }
+        EntryKind::Split { bank } => format!("split\t{}", escape(bank)),
+        EntryKind::Fee { trigger } => format!("fee\t{}", encode_trigger(*trigger)),
+        EntryKind::CreditLine { credit_line } => {
+            format!("credit-line\t{}", credit_line.minor())
+        }
+        EntryKind::Clearing { bank, user } => {
+            format!("clearing\t{}\t{}", escape(bank), escape(user))
+        }
+        EntryKind::Status { status } => format!("status\t{}", status),
+        EntryKind::Limits { limits } => format!(
+            "limits\t{}\t{}\t{}",
+            optional(limits.per_transfer.map(Money::minor)),
+            optional(limits.daily_total.map(Money::minor)),
+            optional(limits.daily_count)
+        ),
+        EntryKind::Settlement { house, net } => {
+            format!("settlement\t{}\t{}", escape(house), net.minor())
+        }
+        EntryKind::LoanDisbursement { loan } => format!("loan-disbursement\t{}", loan.0),
+        EntryKind::LoanInterest { loan } => format!("loan-interest\t{}", loan.0),
+        EntryKind::LoanPrincipal { loan } => format!("loan-principal\t{}", loan.0),
+        EntryKind::LoanTransfer {
+            loan,
+            bank,
+            outstanding,
+        } => format!(
+            "loan-transfer\t{}\t{}\t{}",
+            loan.0,
+            escape(bank),
+            outstanding.minor()
+        ),
+    };
+    format!(
+        "entry\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+        entry.seq,
+        optional(entry.from.as_deref().map(escape)),
+        optional(entry.to.as_deref().map(escape)),
+        entry.amount.minor(),
+        optional(entry.from_balance.map(Money::minor)),
+        optional(entry.to_balance.map(Money::minor)),
+        kind
+    )
+}
+
+fn decode_entry(r: &Record, version: u32) -> Result<LedgerEntry, PersistError> {
+    let kind = match r.field(6)? {
+        "open" => EntryKind::Open {
+            credit_line: r.money(7)?,
+        },
+        "transfer" => EntryKind::Transfer,
+        "interest" => EntryKind::Interest {
+            carry: if version >= 3 { r.num(7)? } else { 0 },
+        },
+        "merge" => EntryKind::Merge {
+            bank: r.string(7)?,
+            credit_line: r.money(8)?,
+        },
+        "split" => EntryKind::Split { bank: r.string(7)? },
+        "credit-line" => EntryKind::CreditLine {
+            credit_line: r.money(7)?,
+        },
+        "fee" => EntryKind::Fee {
+            trigger: decode_trigger(r, 7)?,
+        },
+        "clearing" => EntryKind::Clearing {
+            bank: r.string(7)?,
+            user: r.string(8)?,
+        },
+        "status" => EntryKind::Status {
+            status: match r.field(7)? {
+                "active" => AccountStatus::Active,
+                "frozen" => AccountStatus::Frozen,
+                "closed" => AccountStatus::Closed,
+                other => return Err(r.malformed(format!("unknown status {:?}", other))),
+            },
+        },
+        "limits" => EntryKind::Limits {
+            limits: TransferLimits {
+                per_transfer: r.optional(7, minor)?,
+                daily_total: r.optional(8, minor)?,
+                daily_count: r.optional(9, |s| s.parse().ok())?,
+            },
+        },
+        "settlement" => EntryKind::Settlement {
+            house: r.string(7)?,
+            net: r.money(8)?,
+        },
+        "loan-disbursement" => EntryKind::LoanDisbursement {
+            loan: LoanId(r.num(7)?),
+        },
+        "loan-interest" => EntryKind::LoanInterest {
+            loan: LoanId(r.num(7)?),
+        },
+        "loan-principal" => EntryKind::LoanPrincipal {
+            loan: LoanId(r.num(7)?),
+        },
+        "loan-transfer" => EntryKind::LoanTransfer {
+            loan: LoanId(r.num(7)?),
+            bank: r.string(8)?,
+            outstanding: r.money(9)?,
+        },
+        other => return Err(r.malformed(format!("unknown entry kind {:?}", other))),
+    };
+    Ok(LedgerEntry {
+        seq: r.num(0)?,
+        kind,
+        from: r.optional(1, unescape)?,
+        to: r.optional(2, unescape)?,
+        amount: r.money(3)?,
+        from_balance: r.optional(4, minor)?,
+        to_balance: r.optional(5, minor)?,
+    })
+}
+
+/// Limits and usage are only stored from version 11
+fn decode_user(r: &Record, version: u32) -> Result<User, PersistError> {
+    let mut user = User::new(r.string(0)?, r.money(1)?, r.money(2)?);
+    if version >= 11 {
+        user.limits = TransferLimits {
+            per_transfer: r.optional(3, minor)?,
+            daily_total: r.optional(4, minor)?,
+            daily_count: r.optional(5, |s| s.parse().ok())?,
+        };
+        user.usage.day = r.num(6)?;
+        user.usage.total = r.money(7)?;
+        user.usage.count = r.num(8)?;
+    }
+    Ok(user)
+}
+
+fn decode_order(r: &Record) -> Result<StandingOrder, PersistError> {
+    let mut order = StandingOrder::new(
+        &r.string(1)?,
+        &r.string(2)?,
+        r.money(3)?,
+        r.num(4)?,
+        r.num(5)?,
+    );
+    order.id = OrderId(r.num(0)?);
+    order.remaining = r.optional(6, |s| s.parse().ok())?;
+    Ok(order)
+}
+
+/// A loan record followed by one record per installment
+fn encode_loan(loan: &Loan) -> String {
+    let terms = &loan.terms;
+    let mut out = format!(
+        "loan\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+        loan.id.0,
+        escape(&loan.borrower),
+        terms.principal.minor(),
+        terms.annual_rate,
+        terms.term,
+        terms.frequency,
+        loan.start,
+        loan.outstanding.minor()
+    );
+    for installment in &loan.installments {
+        out += &format!(
+            "installment\t{}\t{}\t{}\t{}\n",
+            installment.due,
+            installment.interest.minor(),
+            installment.principal.minor(),
+            if installment.paid { "paid" } else { "unpaid" }
+        );
+    }
+    out
+}
+
+fn decode_loan(r: &Record) -> Result<Loan, PersistError> {
+    let frequency = match r.field(5)? {
+        "weekly" => PaymentFrequency::Weekly,
+        "monthly" => PaymentFrequency::Monthly,
+        "quarterly" => PaymentFrequency::Quarterly,
+        other => return Err(r.malformed(format!("unknown frequency {:?}", other))),
+    };
+    Ok(Loan {
+        borrower: r.string(1)?,
+        terms: LoanTerms::new(r.money(2)?, r.num(3)?, r.num(4)?, frequency),
+        start: r.num(6)?,
+        outstanding: r.money(7)?,
+        installments: Vec::new(),
+        id: LoanId(r.num(0)?),
+    })
+}
+
+fn decode_installment(r: &Record) -> Result<Installment, PersistError> {
+    Ok(Installment {
+        due: r.num(0)?,
+        interest: r.money(1)?,
+        principal: r.money(2)?,
+        paid: match r.field(3)? {
+            "paid" => true,
+            "unpaid" => false,
+            other => return Err(r.malformed(format!("unknown installment state {:?}", other))),
+        },
+    })
+}
+
+fn encode_trigger(trigger: FeeTrigger) -> String {
+    match trigger {
+        FeeTrigger::Transfer => "transfer".to_string(),
+        FeeTrigger::Overdraft => "overdraft".to_string(),
+        FeeTrigger::Maintenance { every } => format!("maintenance\t{}", every),
+    }
+}
+
+/// Trigger starting at field `idx`; maintenance takes a second field
+fn decode_trigger(r: &Record, idx: usize) -> Result<FeeTrigger, PersistError> {
+    match r.field(idx)? {
+        "transfer" => Ok(FeeTrigger::Transfer),
+        "overdraft" => Ok(FeeTrigger::Overdraft),
+        "maintenance" => Ok(FeeTrigger::Maintenance {
+            every: r.num(idx + 1)?,
+        }),
+        other => Err(r.malformed(format!("unknown fee trigger {:?}", other))),
+    }
+}
+
+fn encode_fee(fee: &Fee) -> String {
+    match fee {
+        Fee::Flat(amount) => format!("flat {}", amount.minor()),
+        Fee::Percent(bps) => format!("percent {}", bps),
+        Fee::Capped { fee, min, max } => {
+            format!("capped {} {} {}", min.minor(), max.minor(), encode_fee(fee))
+        }
+        Fee::Tiered(tiers) => {
+            let mut out = format!("tiered {}", tiers.len());
+            for tier in tiers {
+                let up_to = tier
+                    .up_to
+                    .map_or("-".to_string(), |m| m.minor().to_string());
+                out += &format!(" {} {}", up_to, encode_fee(&tier.fee));
+            }
+            out
+        }
+    }
+}
+
+fn decode_fee(r: &Record, idx: usize) -> Result<Fee, PersistError> {
+    let field = r.field(idx)?;
+    let mut words = field.split(' ');
+    match parse_fee(&mut words) {
+        Some(fee) if words.next().is_none() => Ok(fee),
+        _ => Err(r.malformed(format!("invalid fee {:?}", field))),
+    }
+}
+
+fn parse_fee<'a>(words: &mut impl Iterator<Item = &'a str>) -> Option<Fee> {
+    let fee = match words.next()? {
+        "flat" => Fee::Flat(minor(words.next()?)?),
+        "percent" => Fee::Percent(words.next()?.parse().ok()?),
+        "capped" => {
+            let min = minor(words.next()?)?;
+            let max = minor(words.next()?)?;
+            Fee::Capped {
+                fee: Box::new(parse_fee(words)?),
+                min,
+                max,
+            }
+        }
+        "tiered" => {
+            let count: usize = words.next()?.parse().ok()?;
+            let mut tiers = Vec::new();
+            for _ in 0..count {
+                let up_to = match words.next()? {
+                    "-" => None,
+                    word => Some(minor(word)?),
+                };
+                let fee = parse_fee(words)?;
+                tiers.push(FeeTier { up_to, fee });
+            }
+            Fee::Tiered(tiers)
+        }
+        _ => return None,
+    };
+    Some(fee)
+}
+
+fn encode_period(period: CompoundingPeriod) -> &'static str {
+    match period {
+        CompoundingPeriod::Daily => "daily",
+        CompoundingPeriod::Monthly => "monthly",
+        CompoundingPeriod::Quarterly => "quarterly",
+    }
+}
+
+fn decode_period(r: &Record) -> Result<CompoundingPeriod, PersistError> {
+    match r.field(0)? {
+        "daily" => Ok(CompoundingPeriod::Daily),
+        "monthly" => Ok(CompoundingPeriod::Monthly),
+        "quarterly" => Ok(CompoundingPeriod::Quarterly),
+        other => Err(r.malformed(format!("unknown period {:?}", other))),
+    }
+}
+
+fn encode_day_count(day_count: DayCount) -> &'static str {
+    match day_count {
+        DayCount::Actual365 => "act365",
+        DayCount::Actual360 => "act360",
+        DayCount::Thirty360 => "30/360",
+    }
+}
+
+fn decode_day_count(r: &Record) -> Result<DayCount, PersistError> {
+    match r.field(1)? {
+        "act365" => Ok(DayCount::Actual365),
+        "act360" => Ok(DayCount::Actual360),
+        "30/360" => Ok(DayCount::Thirty360),
+        other => Err(r.malformed(format!("unknown day count {:?}", other))),
+    }
+}
+
+/// Fields of one record, with the record tag already stripped
+struct Record<'a> {
+    line: usize,
+    fields: &'a [&'a str],
+}
+
+impl Record<'_> {
+    fn malformed(&self, reason: String) -> PersistError {
+        PersistError::Malformed {
+            line: self.line,
+            reason,
+        }
+    }
+
+    fn field(&self, idx: usize) -> Result<&str, PersistError> {
+        self.fields
+            .get(idx)
+            .copied()
+            .ok_or_else(|| self.malformed(format!("missing field {}", idx + 1)))
+    }
+
+    fn string(&self, idx: usize) -> Result<String, PersistError> {
+        unescape(self.field(idx)?).ok_or_else(|| self.malformed("bad escape".to_string()))
+    }
+
+    fn num<T: std::str::FromStr>(&self, idx: usize) -> Result<T, PersistError> {
+        let field = self.field(idx)?;
+        field
+            .parse()
+            .map_err(|_| self.malformed(format!("invalid number {:?}", field)))
+    }
+
+    fn money(&self, idx: usize) -> Result<Money, PersistError> {
+        self.num(idx).map(Money::from_minor)
+    }
+
+    fn optional<T>(
+        &self,
+        idx: usize,
+        parse: impl Fn(&str) -> Option<T>,
+    ) -> Result<Option<T>, PersistError> {
+        match self.field(idx)? {
+            "-" => Ok(None),
+            field => field
+                .strip_prefix('+')
+                .and_then(parse)
+                .map(Some)
+                .ok_or_else(|| self.malformed(format!("invalid optional field {:?}", field))),
+        }
+    }
+}
+
+/// Splits off and checks the trailing checksum line, returning the body
+fn verify_checksum(text: &str) -> Result<&str, PersistError> {
+    let trimmed = text.strip_suffix('\n').ok_or(PersistError::Truncated)?;
+    let split = trimmed.rfind('\n').map_or(0, |i| i + 1);
+    let (body, last) = trimmed.split_at(split);
+
+    let expected = match last.split_once('\t') {
+        Some(("checksum", hex)) => {
+            u64::from_str_radix(hex, 16).map_err(|_| PersistError::ChecksumMismatch)?
+        }
+        _ => return Err(PersistError::Truncated),
+    };
+    if checksum(body.as_bytes()) != expected {
+        return Err(PersistError::ChecksumMismatch);
+    }
+    Ok(body)
+}
+
+/// 64-bit FNV-1a
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Parses an amount stored in minor units
+fn minor(s: &str) -> Option<Money> {
+    s.parse().ok().map(Money::from_minor)
+}
+
+fn optional<T: fmt::Display>(value: Option<T>) -> String {
+    match value {
+        Some(v) => format!("+{}", v),
+        None => "-".to_string(),
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(s: &str) -> Option<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next()? {
+                '\\' => out.push('\\'),
+                't' => out.push('\t'),
+                'n' => out.push('\n'),
+                'r' => out.push('\r'),
+                _ => return None,
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::{ClearingHouse, Payment};
+
+    fn sample_bank() -> Bank {
+        let mut bank = Bank::new("Tab\tBank\\".to_string(), 500, 1000);
+        bank.add_user(User::new("Alice".to_string(), 5000.into(), 2000.into()))
+            .unwrap();
+        bank.add_user(User::new(
+            "Bob\nSmith".to_string(),
+            3000.into(),
+            (-500).into(),
+        ))
+        .unwrap();
+        bank.transfer_funds("Alice", "Bob\nSmith", 700.into())
+            .unwrap();
+        bank.accrue_interest().unwrap();
+
+        let mut other = Bank::new("Other".to_string(), 600, 900);
+        other
+            .add_user(User::new("Charlie".to_string(), 2000.into(), 1500.into()))
+            .unwrap();
+        bank.merge_bank(other).unwrap();
+        let spun = bank
+            .split_users("Spun\tOff".to_string(), 0, 0, &["Charlie"])
+            .unwrap();
+
+        let mut house = ClearingHouse::new("House\n".to_string());
+        let name = bank.name.clone();
+        house.add_bank(bank).unwrap();
+        house.add_bank(spun).unwrap();
+        let payment = Payment::new(&name, "Alice", "Spun\tOff", "Charlie", 25.into());
+        house.submit(payment).unwrap();
+        house.settle();
+        let mut bank = house.remove_bank(&name).unwrap();
+        bank.freeze("Bob\nSmith").unwrap();
+        let terms = LoanTerms::new(1200.into(), 900, 6, PaymentFrequency::Weekly);
+        bank.open_loan("Alice", terms).unwrap();
+        let limits = TransferLimits {
+            per_transfer: Some(5000.into()),
+            daily_total: Some(8000.into()),
+            daily_count: None,
+        };
+        bank.set_transfer_limits("Alice", limits).unwrap();
+        bank.transfer_funds("Alice", "Bob\nSmith", 100.into())
+            .unwrap();
+        bank
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let bank = sample_bank();
+        let decoded = Bank::deserialize(&bank.serialize()).unwrap();
+        assert_eq!(decoded, bank);
+    }
+
+    #[test]
+    fn test_round_trip_with_schedule() {
+        let mut bank = sample_bank();
+        bank.interest_schedule = Some(InterestSchedule::new(
+            CompoundingPeriod::Monthly,
+            DayCount::Thirty360,
+        ));
+        bank.accrue_interest().unwrap();
+        let decoded = Bank::deserialize(&bank.serialize()).unwrap();
+        assert_eq!(decoded, bank);
+    }
+
+    #[test]
+    fn test_round_trip_with_standing_orders() {
+        let mut bank = sample_bank();
+        let order = StandingOrder::new("Alice", "Bob\nSmith", 100.into(), 7, 3).times(4);
+        bank.add_standing_order(order).unwrap();
+        let order = StandingOrder::new("Bob\nSmith", "Alice", 5.into(), 1, 1);
+        let id = bank.add_standing_order(order).unwrap();
+        bank.advance_clock(10);
+        bank.cancel_standing_order(id).unwrap();
+
+        let decoded = Bank::deserialize(&bank.serialize()).unwrap();
+        assert_eq!(decoded.today(), 10);
+        assert_eq!(decoded.standing_orders()[0].remaining, Some(2));
+        assert_eq!(decoded, bank);
+    }
+
+    #[test]
+    fn test_round_trip_with_fees() {
+        let mut bank = sample_bank();
+        let tiered = Fee::Tiered(vec![
+            FeeTier {
+                up_to: Some(1000.into()),
+                fee: Fee::Flat(10.into()),
+            },
+            FeeTier {
+                up_to: None,
+                fee: Fee::Capped {
+                    fee: Box::new(Fee::Percent(25)),
+                    min: 0.into(),
+                    max: 500.into(),
+                },
+            },
+        ]);
+        bank.fees = vec![
+            FeeRule::new(FeeTrigger::Transfer, tiered),
+            FeeRule::new(FeeTrigger::Maintenance { every: 30 }, Fee::Flat(1.into())),
+        ];
+        bank.transfer_funds("Alice", "Bob\nSmith", 2000.into())
+            .unwrap();
+        bank.advance_clock(30);
+        assert!(bank.fee_revenue().is_positive());
+
+        let decoded = Bank::deserialize(&bank.serialize()).unwrap();
+        assert_eq!(decoded, bank);
+    }
+
+    #[test]
+    fn test_migrates_version_2() {
+        let body = "p32-bank\t2\nbank\tB\t0\t1000\nuser\tAlice\t0\t110\n\
+                    entry\t0\t-\t+Alice\t100\t-\t+100\topen\t0\n\
+                    entry\t1\t-\t+Alice\t10\t-\t+110\tinterest\n";
+        let text = format!("{}checksum\t{:016x}\n", body, checksum(body.as_bytes()));
+        let bank = Bank::deserialize(&text).unwrap();
+        assert_eq!(bank.users()[0].balance, Money::from_minor(110));
+        assert_eq!(bank.interest_schedule, None);
+    }
+
+    #[test]
+    fn test_migrates_version_11_limits() {
+        let body = "p32-bank\t11\nbank\tB\t0\t0\nclock\t3\t1\t1\n\
+                    user\tAlice\t0\t100\t+50\t-\t+2\t3\t20\t1\n\
+                    entry\t0\t-\t+Alice\t100\t-\t+100\topen\t0\n";
+        let text = format!("{}checksum\t{:016x}\n", body, checksum(body.as_bytes()));
+        let bank = Bank::deserialize(&text).unwrap();
+        let alice = &bank.users()[0];
+        assert_eq!(alice.limits.per_transfer, Some(Money::from_minor(50)));
+        assert_eq!(alice.limits.daily_count, Some(2));
+        assert_eq!(alice.usage_on(3).count, 1);
+    }
+
+    #[test]
+    fn test_save_and_load_file() {
+        let bank = sample_bank();
+        let path = std::env::temp_dir().join(format!("p32-persist-{}.bank", std::process::id()));
+        bank.save(&path).unwrap();
+        let loaded = Bank::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, bank);
+    }
+
+    #[test]
+    fn test_rejects_truncated_file() {
+        let text = sample_bank().serialize();
+        let cut = &text[..text.len() / 2];
+        assert!(matches!(
+            Bank::deserialize(cut),
+            Err(PersistError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn test_rejects_corrupted_file() {
+        let text = sample_bank().serialize().replacen("2000", "9000", 1);
+        assert!(matches!(
+            Bank::deserialize(&text),
+            Err(PersistError::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_rejects_future_version() {
+        let body = "p32-bank\t99\nbank\tX\t0\t0\n";
+        let text = format!("{}checksum\t{:016x}\n", body, checksum(body.as_bytes()));
+        assert!(matches!(
+            Bank::deserialize(&text),
+            Err(PersistError::UnsupportedVersion(99))
+        ));
+    }
+
+    #[test]
+    fn test_migrates_version_1() {
+        let body = "p32-bank\t1\nbank\tOld Bank\t500\t1000\nuser\tAlice\t5000\t2000\nuser\tBob\t3000\t-500\n";
+        let text = format!("{}checksum\t{:016x}\n", body, checksum(body.as_bytes()));
+        let bank = Bank::deserialize(&text).unwrap();
+
+        let mut expected = Bank::new("Old Bank".to_string(), 500, 1000);
+        expected
+            .add_user(User::new("Alice".to_string(), 5000.into(), 2000.into()))
+            .unwrap();
+        expected
+            .add_user(User::new("Bob".to_string(), 3000.into(), (-500).into()))
+            .unwrap();
+        assert_eq!(bank, expected);
+        assert_eq!(bank.ledger().len(), 2);
+    }
+}
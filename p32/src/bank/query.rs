@@ -0,0 +1,253 @@
+use std::cmp::Ordering;
+use std::ops::RangeBounds;
+
+use super::{AccountStatus, Bank, Money, User, normalize_name};
+
+/// What [`UserQuery::sort_by`] orders users by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Id,
+    Name,
+    Balance,
+    CreditLine,
+    AvailableFunds,
+    /// Users without a credit line sort before all others
+    Utilisation,
+}
+
+impl SortKey {
+    fn compare(self, a: &User, b: &User) -> Ordering {
+        match self {
+            SortKey::Id => a.id.cmp(&b.id),
+            SortKey::Name => normalize_name(&a.name).cmp(&normalize_name(&b.name)),
+            SortKey::Balance => a.balance.cmp(&b.balance),
+            SortKey::CreditLine => a.credit_line.cmp(&b.credit_line),
+            SortKey::AvailableFunds => a.available_funds().cmp(&b.available_funds()),
+            SortKey::Utilisation => a.utilisation_bps().cmp(&b.utilisation_bps()),
+        }
+    }
+}
+
+type Filter<'a> = Box<dyn Fn(&User) -> bool + 'a>;
+
+/// Filters, sorts and pages the users of a bank, see [`Bank::query`].
+///
+/// Filters combine with AND. Without a sort, users come in account order;
+/// ties in a sort keep account order too.
+pub struct UserQuery<'a> {
+    users: &'a [User],
+    filters: Vec<Filter<'a>>,
+    sort: Option<(SortKey, bool)>, // (key, descending)
+    offset: usize,
+    limit: Option<usize>,
+}
+
+impl Bank {
+    /// Starts a query over every user, closed accounts included
+    pub fn query(&self) -> UserQuery<'_> {
+        UserQuery {
+            users: &self.users,
+            filters: Vec::new(),
+            sort: None,
+            offset: 0,
+            limit: None,
+        }
+    }
+}
+
+impl<'a> UserQuery<'a> {
+    /// Keeps users matching an arbitrary predicate
+    pub fn filter(mut self, pred: impl Fn(&User) -> bool + 'a) -> Self {
+        self.filters.push(Box::new(pred));
+        self
+    }
+
+    /// Keeps users whose balance lies in `range`, e.g. `..Money::ZERO` for debtors
+    pub fn balance(self, range: impl RangeBounds<Money> + 'a) -> Self {
+        self.filter(move |u| range.contains(&u.balance))
+    }
+
+    /// Keeps overdrawn users
+    pub fn debtors(self) -> Self {
+        self.filter(|u| u.balance.is_negative())
+    }
+
+    /// Keeps users using at least `bps` of their credit line; users without
+    /// one never match
+    pub fn utilisation_at_least(self, bps: u64) -> Self {
+        self.filter(move |u| u.utilisation_bps().is_some_and(|u| u >= bps))
+    }
+
+    /// Keeps users whose name starts with `prefix`, ignoring case and
+    /// leading whitespace
+    pub fn name_prefix(self, prefix: &str) -> Self {
+        let prefix = normalize_name(prefix);
+        self.filter(move |u| normalize_name(&u.name).starts_with(&prefix))
+    }
+
+    pub fn status(self, status: AccountStatus) -> Self {
+        self.filter(move |u| u.status == status)
+    }
+
+    /// Orders by `key`, smallest first
+    pub fn sort_by(mut self, key: SortKey) -> Self {
+        self.sort = Some((key, false));
+        self
+    }
+
+    /// Orders by `key`, largest first
+    pub fn sort_by_desc(mut self, key: SortKey) -> Self {
+        self.sort = Some((key, true));
+        self
+    }
+
+    /// Skips the first `offset` matches
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Returns at most `limit` matches, e.g. for a top-N list
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Page `page` (from 0) of `size` matches
+    pub fn page(self, page: usize, size: usize) -> Self {
+        self.offset(page.saturating_mul(size)).limit(size)
+    }
+
+    /// Number of matching users, ignoring offset and limit
+    pub fn count(&self) -> usize {
+        self.users.iter().filter(|u| self.matches(u)).count()
+    }
+
+    pub fn iter(&self) -> std::vec::IntoIter<&'a User> {
+        let mut users: Vec<&'a User> = self.users.iter().filter(|u| self.matches(u)).collect();
+        if let Some((key, descending)) = self.sort {
+            users.sort_by(|a, b| {
+                let order = key.compare(a, b);
+                if descending { order.reverse() } else { order }
+            });
+        }
+        users.drain(..self.offset.min(users.len()));
+        if let Some(limit) = self.limit {
+            users.truncate(limit);
+        }
+        users.into_iter()
+    }
+
+    /// Names of the matching users, in query order
+    pub fn names(&self) -> Vec<&'a str> {
+        self.iter().map(|u| u.name.as_str()).collect()
+    }
+
+    fn matches(&self, user: &User) -> bool {
+        self.filters.iter().all(|f| f(user))
+    }
+}
+
+impl<'a> IntoIterator for UserQuery<'a> {
+    type Item = &'a User;
+    type IntoIter = std::vec::IntoIter<&'a User>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &UserQuery<'a> {
+    type Item = &'a User;
+    type IntoIter = std::vec::IntoIter<&'a User>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::testing;
+
+    fn sample_bank() -> Bank {
+        let users = [
+            ("Alice", 0, 500),
+            ("Albert", 1000, -800),
+            ("Bob", 1000, -200),
+            ("Carol", 200, 0),
+            ("alfred", 0, 50),
+        ];
+        testing::bank("Query Bank", (0, 0), &users)
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut bank = sample_bank();
+        assert_eq!(bank.query().debtors().names(), vec!["Albert", "Bob"]);
+        assert_eq!(
+            bank.query()
+                .balance(Money::ZERO..=Money::from_minor(50))
+                .names(),
+            vec!["Carol", "alfred"]
+        );
+        assert_eq!(
+            bank.query().name_prefix("AL").names(),
+            vec!["Alice", "Albert", "alfred"]
+        );
+        assert_eq!(
+            bank.query().utilisation_at_least(5000).names(),
+            vec!["Albert"]
+        );
+
+        bank.transfer_funds("alfred", "Alice", 50.into()).unwrap();
+        bank.close_account("alfred").unwrap();
+        let query = bank.query().name_prefix("al").status(AccountStatus::Active);
+        assert_eq!(query.count(), 2);
+        assert_eq!(query.names(), vec!["Alice", "Albert"]);
+    }
+
+    #[test]
+    fn test_sort_and_pages() {
+        let bank = sample_bank();
+        assert_eq!(
+            bank.query().sort_by(SortKey::Balance).limit(2).names(),
+            vec!["Albert", "Bob"]
+        );
+        assert_eq!(
+            bank.query().sort_by_desc(SortKey::Balance).limit(1).names(),
+            vec!["Alice"]
+        );
+        assert_eq!(
+            bank.query().sort_by(SortKey::Name).names(),
+            vec!["Albert", "alfred", "Alice", "Bob", "Carol"]
+        );
+        // Equal utilisation keeps account order
+        assert_eq!(
+            bank.query().sort_by(SortKey::Utilisation).names(),
+            vec!["Alice", "alfred", "Carol", "Bob", "Albert"]
+        );
+
+        let pages: Vec<Vec<_>> = (0..3)
+            .map(|p| bank.query().sort_by(SortKey::Id).page(p, 2).names())
+            .collect();
+        assert_eq!(
+            pages,
+            vec![
+                vec!["Alice", "Albert"],
+                vec!["Bob", "Carol"],
+                vec!["alfred"]
+            ]
+        );
+        assert!(bank.query().offset(10).names().is_empty());
+
+        let total: i64 = bank
+            .query()
+            .debtors()
+            .into_iter()
+            .map(|u| u.balance().minor())
+            .sum();
+        assert_eq!(total, -1000);
+    }
+}
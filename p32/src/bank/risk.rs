@@ -0,0 +1,364 @@
+use std::fmt::Write;
+
+use super::statement::csv_field;
+use super::{AccountStatus, Bank, Money, SortKey, User};
+
+/// Credit-line use of one open account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Exposure {
+    pub name: String,
+    pub status: AccountStatus,
+    pub balance: Money,
+    pub credit_line: Money,
+    pub utilisation_bps: Option<u64>, // see User::utilisation_bps
+}
+
+impl Exposure {
+    /// Amount the account is overdrawn by, zero if it is not
+    pub fn overdraft(&self) -> Money {
+        (-self.balance).max(Money::ZERO)
+    }
+}
+
+/// How much of a total the largest accounts hold, in basis points
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Concentration {
+    pub top1_bps: u64,
+    pub top5_bps: u64,
+    /// Herfindahl-Hirschman index: sum of squared shares, 0 to 10000
+    pub hhi: u64,
+}
+
+impl Concentration {
+    fn of(mut amounts: Vec<Money>) -> Self {
+        let total: i128 = amounts.iter().map(|m| m.minor() as i128).sum();
+        if total == 0 {
+            return Self::default();
+        }
+        amounts.sort_unstable_by(|a, b| b.cmp(a));
+        let share = |amount: i128| (amount * 10_000 / total) as u64;
+        let top = |n: usize| share(amounts.iter().take(n).map(|m| m.minor() as i128).sum());
+        let hhi = amounts
+            .iter()
+            .map(|m| share(m.minor() as i128).pow(2))
+            .sum::<u64>()
+            / 10_000;
+        Self {
+            top1_bps: top(1),
+            top5_bps: top(5),
+            hhi,
+        }
+    }
+}
+
+/// Balance-sheet and credit-risk summary of a bank, see [`Bank::risk_report`].
+///
+/// Positive balances are liabilities of the bank (accounts in credit) and
+/// overdrafts are its assets (accounts in debit). Totals saturate at
+/// [`Money::MAX`] like [`Bank::calc_balance`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RiskReport {
+    pub bank: String,
+    pub day: u64,
+    pub liabilities: Money,  // including fee revenue
+    pub assets: Money,       // including loans outstanding
+    pub net_position: Money, // liabilities - assets
+    pub accounts: usize,     // open accounts, frozen included
+    pub in_credit: usize,
+    pub in_debit: usize,
+    pub frozen: usize,
+    pub total_credit_lines: Money,
+    /// Total overdraft over total credit lines, in basis points
+    pub utilisation_bps: Option<u64>,
+    pub loans_outstanding: Money,
+    pub loans_in_arrears: usize,
+    pub arrears: Money,                   // overdue installments over all loans
+    pub exposures: Vec<Exposure>,         // every open account, in account order
+    pub largest_exposures: Vec<Exposure>, // most overdrawn first
+    pub asset_concentration: Concentration,
+    pub deposit_concentration: Concentration,
+}
+
+impl Bank {
+    /// Risk report over every account that is not closed, listing the `top`
+    /// most overdrawn accounts as the largest exposures
+    pub fn risk_report(&self, top: usize) -> RiskReport {
+        let (liabilities, assets) = self.calc_balance();
+        let open = |u: &User| u.status != AccountStatus::Closed;
+        let exposure = |u: &User| Exposure {
+            name: u.name.clone(),
+            status: u.status,
+            balance: u.balance,
+            credit_line: u.credit_line,
+            utilisation_bps: u.utilisation_bps(),
+        };
+        let exposures: Vec<Exposure> = self
+            .query()
+            .filter(open)
+            .into_iter()
+            .map(exposure)
+            .collect();
+
+        let total_credit_lines = exposures
+            .iter()
+            .fold(Money::ZERO, |sum, e| sum.saturating_add(e.credit_line));
+        let largest = self
+            .query()
+            .filter(open)
+            .debtors()
+            .sort_by(SortKey::Balance)
+            .limit(top)
+            .into_iter()
+            .map(exposure)
+            .collect();
+
+        let overdue: Vec<Money> = self
+            .loans
+            .iter()
+            .map(|l| l.arrears(self.today))
+            .filter(|a| a.is_positive())
+            .collect();
+        let overdrafts: Vec<Money> = exposures.iter().map(Exposure::overdraft).collect();
+        let overdraft_total = overdrafts
+            .iter()
+            .fold(Money::ZERO, |sum, &o| sum.saturating_add(o));
+        let deposits = exposures
+            .iter()
+            .map(|e| e.balance.max(Money::ZERO))
+            .collect();
+        RiskReport {
+            bank: self.name.clone(),
+            day: self.today,
+            liabilities,
+            assets,
+            // Both totals are non-negative, so this cannot overflow
+            net_position: liabilities.saturating_add(-assets),
+            accounts: exposures.len(),
+            in_credit: exposures.iter().filter(|e| e.balance.is_positive()).count(),
+            in_debit: exposures.iter().filter(|e| e.balance.is_negative()).count(),
+            frozen: exposures
+                .iter()
+                .filter(|e| e.status == AccountStatus::Frozen)
+                .count(),
+            total_credit_lines,
+            utilisation_bps: utilisation(overdraft_total, total_credit_lines),
+            loans_outstanding: self.loans_outstanding(),
+            loans_in_arrears: overdue.len(),
+            arrears: overdue
+                .iter()
+                .fold(Money::ZERO, |sum, &a| sum.saturating_add(a)),
+            exposures,
+            largest_exposures: largest,
+            asset_concentration: Concentration::of(overdrafts),
+            deposit_concentration: Concentration::of(deposits),
+        }
+    }
+}
+
+/// `overdraft` over `credit_line` in basis points
+pub(super) fn utilisation(overdraft: Money, credit_line: Money) -> Option<u64> {
+    if !credit_line.is_positive() {
+        return None;
+    }
+    let bps = overdraft.minor() as i128 * 10_000 / credit_line.minor() as i128;
+    Some(bps as u64)
+}
+
+/// Basis points as a percentage with two decimals, `-` if absent
+fn percent(bps: Option<u64>) -> String {
+    match bps {
+        Some(bps) => format!("{}.{:02}%", bps / 100, bps % 100),
+        None => "-".to_string(),
+    }
+}
+
+impl RiskReport {
+    /// Plain-text rendering for the daily risk review
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Risk report for {} on day {}", self.bank, self.day);
+        let _ = writeln!(out, "{:<28}{:>20}", "Liabilities", self.liabilities);
+        let _ = writeln!(out, "{:<28}{:>20}", "Assets", self.assets);
+        let _ = writeln!(out, "{:<28}{:>20}", "Net position", self.net_position);
+        let _ = writeln!(
+            out,
+            "Accounts: {} open, {} in credit, {} in debit, {} frozen",
+            self.accounts, self.in_credit, self.in_debit, self.frozen
+        );
+        let _ = writeln!(out, "{:<28}{:>20}", "Credit lines", self.total_credit_lines);
+        let _ = writeln!(
+            out,
+            "{:<28}{:>20}",
+            "Utilisation",
+            percent(self.utilisation_bps)
+        );
+        let _ = writeln!(
+            out,
+            "{:<28}{:>20}",
+            "Loans outstanding", self.loans_outstanding
+        );
+        let _ = writeln!(
+            out,
+            "{:<28}{:>20}",
+            format!("Arrears ({} loans)", self.loans_in_arrears),
+            self.arrears
+        );
+        for (label, c) in [
+            ("Asset concentration", self.asset_concentration),
+            ("Deposit concentration", self.deposit_concentration),
+        ] {
+            let _ = writeln!(
+                out,
+                "{}: top 1 {}, top 5 {}, HHI {}",
+                label,
+                percent(Some(c.top1_bps)),
+                percent(Some(c.top5_bps)),
+                c.hhi
+            );
+        }
+        let _ = writeln!(out, "Largest exposures");
+        for e in &self.largest_exposures {
+            let _ = writeln!(
+                out,
+                "  {:<26}{:>16}{:>16}{:>10}",
+                e.name,
+                e.balance,
+                e.credit_line,
+                percent(e.utilisation_bps)
+            );
+        }
+        out
+    }
+
+    /// CSV with a header row, one row per account and a row totalling them.
+    ///
+    /// The total balance only sums the rows, so unlike the net position it
+    /// leaves out fee revenue and loans.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("name,status,balance,credit_line,utilisation_bps\n");
+        for e in &self.exposures {
+            let _ = writeln!(
+                out,
+                "{},{},{},{},{}",
+                csv_field(&e.name),
+                e.status,
+                e.balance,
+                e.credit_line,
+                e.utilisation_bps.map_or(String::new(), |u| u.to_string())
+            );
+        }
+        let balance = self
+            .exposures
+            .iter()
+            .fold(Money::ZERO, |sum, e| sum.saturating_add(e.balance));
+        let _ = writeln!(
+            out,
+            ",total,{},{},{}",
+            balance,
+            self.total_credit_lines,
+            self.utilisation_bps
+                .map_or(String::new(), |u| u.to_string())
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::{LoanTerms, PaymentFrequency, User, testing};
+
+    fn sample_bank() -> Bank {
+        let users = [
+            ("Alice", 0, 6000),
+            ("Bob", 1000, -500),
+            ("Carol", 4000, -3000),
+            ("Dave", 1000, 0),
+            ("Eve, Jr.", 0, 2000),
+        ];
+        testing::bank("Risk Bank", (0, 0), &users)
+    }
+
+    #[test]
+    fn test_risk_report_totals() {
+        let mut bank = sample_bank();
+        bank.freeze("Bob").unwrap();
+        let report = bank.risk_report(1);
+
+        assert_eq!(report.liabilities, Money::from_minor(8000));
+        assert_eq!(report.assets, Money::from_minor(3500));
+        assert_eq!(report.net_position, Money::from_minor(4500));
+        assert_eq!(
+            (
+                report.accounts,
+                report.in_credit,
+                report.in_debit,
+                report.frozen
+            ),
+            (5, 2, 2, 1)
+        );
+        assert_eq!(report.total_credit_lines, Money::from_minor(6000));
+        assert_eq!(report.utilisation_bps, Some(5833));
+
+        assert_eq!(report.largest_exposures.len(), 1);
+        let carol = &report.largest_exposures[0];
+        assert_eq!(carol.name, "Carol");
+        assert_eq!(carol.utilisation_bps, Some(7500));
+        assert_eq!(report.exposures[0].utilisation_bps, None);
+    }
+
+    #[test]
+    fn test_concentration() {
+        let report = sample_bank().risk_report(5);
+        let assets = report.asset_concentration;
+        assert_eq!(assets.top1_bps, 8571);
+        assert_eq!(assets.top5_bps, 10_000);
+        // 85.71^2 + 14.28^2
+        assert_eq!(assets.hhi, 7550);
+        assert_eq!(report.deposit_concentration.top1_bps, 7500);
+
+        let empty = Bank::new("Empty".to_string(), 0, 0).risk_report(5);
+        assert_eq!(empty.asset_concentration, Concentration::default());
+        assert_eq!(empty.utilisation_bps, None);
+    }
+
+    #[test]
+    fn test_loans_in_report() {
+        let mut bank = sample_bank();
+        let terms = LoanTerms::new(1000.into(), 0, 2, PaymentFrequency::Weekly);
+        bank.add_user(User::new("Frank".to_string(), 0.into(), 0.into()))
+            .unwrap();
+        bank.open_loan("Frank", terms).unwrap();
+        bank.transfer_funds("Frank", "Alice", 1000.into()).unwrap();
+        bank.advance_clock(7);
+
+        let report = bank.risk_report(5);
+        assert_eq!(report.assets, Money::from_minor(4500));
+        // Loans do not count against credit lines
+        assert_eq!(report.utilisation_bps, Some(5833));
+        assert_eq!(report.loans_outstanding, Money::from_minor(1000));
+        assert_eq!(report.loans_in_arrears, 1);
+        assert_eq!(report.arrears, Money::from_minor(500));
+        assert!(report.to_text().contains("Arrears (1 loans)"));
+
+        // The CSV total sums the accounts, leaving the loan out
+        assert_eq!(report.net_position, Money::from_minor(4500));
+        let csv = report.to_csv();
+        assert_eq!(csv.lines().last(), Some(",total,55.00,60.00,5833"));
+    }
+
+    #[test]
+    fn test_risk_report_rendering() {
+        let report = sample_bank().risk_report(2);
+        let text = report.to_text();
+        assert!(text.contains("Net position"));
+        assert!(text.contains("58.33%"));
+
+        let csv = report.to_csv();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[3], "Carol,active,-30.00,40.00,7500");
+        assert_eq!(lines[5], "\"Eve, Jr.\",active,20.00,0.00,");
+        assert_eq!(lines[6], ",total,45.00,60.00,5833");
+    }
+}
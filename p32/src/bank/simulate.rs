@@ -0,0 +1,399 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use super::{AccountStatus, Bank, MergePolicy, Money, User, risk};
+
+/// Small seeded generator (SplitMix64), so a simulation depends on nothing
+/// but its seed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..n`; 0 if `n` is 0
+    pub fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+
+    /// Uniform in `1..=max` minor units, zero if `max` is not positive
+    fn amount(&mut self, max: Money) -> Money {
+        match u64::try_from(max.minor()) {
+            Ok(max) if max > 0 => Money::from_minor(self.below(max) as i64 + 1),
+            _ => Money::ZERO,
+        }
+    }
+}
+
+/// What a stress run does, see [`Bank::simulate`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimConfig {
+    pub seed: u64,
+    pub steps: u64,             // one random transfer per step
+    pub users: u32,             // simulated accounts opened before the first step
+    pub opening_balance: Money, // upper bound, drawn per account
+    pub credit_line: Money,     // upper bound, drawn per account
+    pub max_transfer: Money,
+    pub accrue_every: u64, // steps between interest accruals, 0 = never
+    pub merge_every: u64,  // steps between merges of a random bank, 0 = never
+    pub sample_every: u64, // steps between samples, 0 = only at the end
+}
+
+impl SimConfig {
+    pub fn new(seed: u64, steps: u64) -> Self {
+        Self {
+            seed,
+            steps,
+            users: 20,
+            opening_balance: 100_000.into(),
+            credit_line: 50_000.into(),
+            max_transfer: 30_000.into(),
+            accrue_every: 100,
+            merge_every: 1000,
+            sample_every: 10,
+        }
+    }
+}
+
+/// State of the bank after one step of a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    pub step: u64,
+    pub liabilities: Money,
+    pub assets: Money,
+    /// Total overdraft of open accounts over their credit lines, in basis points
+    pub utilisation_bps: Option<u64>,
+    pub rejected: u64, // transfers rejected so far
+}
+
+/// Summary statistics of one series, in minor units or basis points
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SeriesStats {
+    pub min: i64,
+    pub max: i64,
+    pub mean: i64, // rounded towards zero
+    pub p50: i64,
+    pub p95: i64,
+}
+
+impl SeriesStats {
+    /// Statistics of `values`, percentiles by nearest rank; all zero if empty
+    pub fn of(values: &[i64]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_unstable();
+        let rank = |pct: usize| sorted[(pct * sorted.len()).div_ceil(100).max(1) - 1];
+        let sum: i128 = sorted.iter().map(|&v| v as i128).sum();
+        Self {
+            min: sorted[0],
+            max: sorted[sorted.len() - 1],
+            mean: (sum / sorted.len() as i128) as i64,
+            p50: rank(50),
+            p95: rank(95),
+        }
+    }
+}
+
+/// Outcome of [`Bank::simulate`]; equal for equal seeds, configs and banks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimReport {
+    pub bank: String,
+    pub seed: u64,
+    pub steps: u64,
+    pub completed: u64,
+    /// Rejected transfers by [`TransferError::kind`](super::TransferError::kind)
+    pub rejected: BTreeMap<&'static str, u64>,
+    pub accruals: u64,
+    pub failed_accruals: u64,
+    pub merges: u64,
+    pub failed_merges: u64,
+    pub samples: Vec<Sample>,
+    pub liabilities: SeriesStats,
+    pub assets: SeriesStats,
+    pub net_position: SeriesStats, // liabilities - assets
+    pub utilisation: SeriesStats,  // samples without credit lines count as 0
+}
+
+impl Bank {
+    /// Drives this bank through a seeded random run and reports on it.
+    ///
+    /// Opens `config.users` simulated accounts, then makes one random
+    /// transfer per step between any two accounts, accrues interest and
+    /// merges in a small random bank at the configured intervals. A merged
+    /// bank's accounts may share names with ours and are then combined.
+    /// Everything is drawn from `config.seed`, so the same bank and config
+    /// always give the same report.
+    pub fn simulate(&mut self, config: &SimConfig) -> SimReport {
+        let mut rng = Rng::new(config.seed);
+        let mut report = SimReport {
+            bank: self.name.clone(),
+            seed: config.seed,
+            steps: config.steps,
+            completed: 0,
+            rejected: BTreeMap::new(),
+            accruals: 0,
+            failed_accruals: 0,
+            merges: 0,
+            failed_merges: 0,
+            samples: Vec::new(),
+            liabilities: SeriesStats::default(),
+            assets: SeriesStats::default(),
+            net_position: SeriesStats::default(),
+            utilisation: SeriesStats::default(),
+        };
+
+        for n in 0..config.users {
+            let user = random_user(&mut rng, config, n as u64);
+            // A name already taken leaves the existing account in play
+            let _ = self.add_user(user);
+        }
+
+        let mut rejected = 0;
+        for step in 1..=config.steps {
+            if self.users.len() >= 2 {
+                let from = rng.below(self.users.len() as u64) as usize;
+                let to = rng.below(self.users.len() as u64) as usize;
+                let amount = rng.amount(config.max_transfer);
+                let (from, to) = (self.users[from].name.clone(), self.users[to].name.clone());
+                match self.transfer_funds(&from, &to, amount) {
+                    Ok(()) => report.completed += 1,
+                    Err(err) => {
+                        rejected += 1;
+                        *report.rejected.entry(err.kind()).or_default() += 1;
+                    }
+                }
+            }
+            if config.accrue_every > 0 && step % config.accrue_every == 0 {
+                match self.accrue_interest() {
+                    Ok(()) => report.accruals += 1,
+                    Err(_) => report.failed_accruals += 1,
+                }
+            }
+            if config.merge_every > 0 && step % config.merge_every == 0 {
+                let other = self.random_bank(&mut rng, config, report.merges);
+                match self.merge_bank_with(other, MergePolicy::Sum) {
+                    Ok(_) => report.merges += 1,
+                    Err(_) => report.failed_merges += 1,
+                }
+            }
+            let sample = config.sample_every > 0 && step % config.sample_every == 0;
+            if sample || step == config.steps {
+                report.samples.push(self.sample(step, rejected));
+            }
+        }
+
+        let series = |f: fn(&Sample) -> i64| {
+            let values: Vec<i64> = report.samples.iter().map(f).collect();
+            SeriesStats::of(&values)
+        };
+        let liabilities = series(|s| s.liabilities.minor());
+        let assets = series(|s| s.assets.minor());
+        let net_position = series(|s| s.liabilities.saturating_add(-s.assets).minor());
+        let utilisation = series(|s| s.utilisation_bps.unwrap_or(0) as i64);
+        report.liabilities = liabilities;
+        report.assets = assets;
+        report.net_position = net_position;
+        report.utilisation = utilisation;
+        report
+    }
+
+    /// A bank of one to three simulated accounts, named so that some
+    /// collide with ours
+    fn random_bank(&self, rng: &mut Rng, config: &SimConfig, merges: u64) -> Bank {
+        let mut bank = Bank::new(
+            format!("Sim Bank {}", merges + 1),
+            self.credit_interest,
+            self.debit_interest,
+        );
+        let names = config.users as u64 * 2;
+        for _ in 0..=rng.below(3) {
+            let n = rng.below(names.max(1));
+            let user = random_user(rng, config, n);
+            let _ = bank.add_user(user);
+        }
+        bank
+    }
+
+    fn sample(&self, step: u64, rejected: u64) -> Sample {
+        let (liabilities, assets) = self.calc_balance();
+        let open = self
+            .users
+            .iter()
+            .filter(|u| u.status != AccountStatus::Closed);
+        let (overdraft, credit_lines) = open.fold((Money::ZERO, Money::ZERO), |(o, c), u| {
+            let overdraft = (-u.balance).max(Money::ZERO);
+            (o.saturating_add(overdraft), c.saturating_add(u.credit_line))
+        });
+        Sample {
+            step,
+            liabilities,
+            assets,
+            utilisation_bps: risk::utilisation(overdraft, credit_lines),
+            rejected,
+        }
+    }
+}
+
+/// Simulated account `sim-<n>`, opening in credit
+fn random_user(rng: &mut Rng, config: &SimConfig, n: u64) -> User {
+    let credit_line = rng.amount(config.credit_line);
+    let balance = rng.amount(config.opening_balance);
+    User::new(format!("sim-{}", n), credit_line, balance)
+}
+
+impl SimReport {
+    /// Plain-text summary of the run
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "Simulation of {} with seed {} over {} steps",
+            self.bank, self.seed, self.steps
+        );
+        let rejected: u64 = self.rejected.values().sum();
+        let _ = writeln!(
+            out,
+            "Transfers: {} completed, {} rejected",
+            self.completed, rejected
+        );
+        for (kind, count) in &self.rejected {
+            let _ = writeln!(out, "  {:<26}{:>10}", kind, count);
+        }
+        let _ = writeln!(
+            out,
+            "Accruals: {} ({} failed), merges: {} ({} failed)",
+            self.accruals, self.failed_accruals, self.merges, self.failed_merges
+        );
+        let _ = writeln!(
+            out,
+            "{:<16}{:>16}{:>16}{:>16}{:>16}{:>16}",
+            "Series", "min", "mean", "p50", "p95", "max"
+        );
+        for (label, s, is_money) in [
+            ("liabilities", self.liabilities, true),
+            ("assets", self.assets, true),
+            ("net position", self.net_position, true),
+            ("utilisation bps", self.utilisation, false),
+        ] {
+            let format = |v: i64| {
+                if is_money {
+                    Money::from_minor(v).to_string()
+                } else {
+                    v.to_string()
+                }
+            };
+            let _ = writeln!(
+                out,
+                "{:<16}{:>16}{:>16}{:>16}{:>16}{:>16}",
+                label,
+                format(s.min),
+                format(s.mean),
+                format(s.p50),
+                format(s.p95),
+                format(s.max)
+            );
+        }
+        out
+    }
+
+    /// The sampled time series as CSV with a header row
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("step,liabilities,assets,utilisation_bps,rejected\n");
+        for s in &self.samples {
+            let _ = writeln!(
+                out,
+                "{},{},{},{},{}",
+                s.step,
+                s.liabilities,
+                s.assets,
+                s.utilisation_bps.map_or(String::new(), |u| u.to_string()),
+                s.rejected
+            );
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(seed: u64) -> SimConfig {
+        let mut config = SimConfig::new(seed, 2000);
+        config.users = 10;
+        config.merge_every = 500;
+        config
+    }
+
+    #[test]
+    fn test_rng_is_deterministic() {
+        let mut a = Rng::new(7);
+        let mut b = Rng::new(7);
+        let draws: Vec<u64> = (0..5).map(|_| a.below(10)).collect();
+        assert_eq!(draws, (0..5).map(|_| b.below(10)).collect::<Vec<_>>());
+        assert!(draws.iter().all(|&d| d < 10));
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+        assert_eq!(Rng::new(3).below(0), 0);
+    }
+
+    #[test]
+    fn test_same_seed_same_report() {
+        let mut bank = Bank::new("Stress Bank".to_string(), 0, 200);
+        let report = bank.clone().simulate(&config(42));
+        assert_eq!(bank.simulate(&config(42)), report);
+
+        let other = Bank::new("Stress Bank".to_string(), 0, 200).simulate(&config(43));
+        assert_ne!(other, report);
+    }
+
+    #[test]
+    fn test_report_adds_up() {
+        let mut bank = Bank::new("Stress Bank".to_string(), 0, 200);
+        let report = bank.simulate(&config(9));
+        let rejected: u64 = report.rejected.values().sum();
+        assert_eq!(report.completed + rejected, 2000);
+        assert!(report.rejected.contains_key("self-transfer"));
+        assert_eq!(report.accruals, 20);
+        assert_eq!(report.merges + report.failed_merges, 4);
+        assert_eq!(report.samples.len(), 200);
+        assert_eq!(report.samples.last().unwrap().rejected, rejected);
+
+        // The last sample is the bank as it was left
+        let last = report.samples.last().unwrap();
+        assert_eq!((last.liabilities, last.assets), bank.calc_balance());
+        assert!(report.assets.min <= report.assets.p50);
+        assert!(report.assets.p95 <= report.assets.max);
+        assert!(bank.verify().is_empty());
+        assert_eq!(report.to_csv().lines().count(), 201);
+    }
+
+    #[test]
+    fn test_series_stats() {
+        let stats = SeriesStats::of(&[5, 1, 3, 2, 4]);
+        assert_eq!(
+            stats,
+            SeriesStats {
+                min: 1,
+                max: 5,
+                mean: 3,
+                p50: 3,
+                p95: 5,
+            }
+        );
+        assert_eq!(SeriesStats::of(&[]), SeriesStats::default());
+    }
+}
@@ -0,0 +1,287 @@
+use std::error::Error;
+use std::fmt;
+
+use super::{AccountStatus, Bank, EntryKind, Loan, Money, TransferLimits, User};
+
+#[derive(Debug)]
+pub enum SplitError {
+    UserNotFound(String),
+    /// Liabilities and assets of the two banks do not add up to the original's
+    Unbalanced {
+        before: (i128, i128),
+        after: (i128, i128),
+    },
+}
+
+impl fmt::Display for SplitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SplitError::UserNotFound(name) => write!(f, "User {} not found", name),
+            SplitError::Unbalanced { before, after } => write!(
+                f,
+                "Split banks total {}/{} instead of {}/{} (minor units)",
+                after.0, after.1, before.0, before.1
+            ),
+        }
+    }
+}
+
+impl Error for SplitError {}
+
+impl Bank {
+    /// Moves every user matching `pred` into a new bank (a divestiture).
+    ///
+    /// The new bank gets its own name and rates and this bank's interest
+    /// schedule. Balances, unposted interest, credit lines, account states
+    /// and transfer limits move unchanged, with today's usage of the limits
+    /// counted on the new bank's first day. Each moved user leaves a `Split`
+    /// entry here and opens with a `Merge` entry there. Loans of moved users
+    /// move with them. Fails without changing this bank unless the
+    /// liabilities and assets of both banks add up to this bank's before the
+    /// split.
+    ///
+    /// Fee revenue and standing orders stay here; orders of moved users fail
+    /// once they run.
+    pub fn split_off(
+        &mut self,
+        name: String,
+        credit_interest: u64,
+        debit_interest: u64,
+        mut pred: impl FnMut(&User) -> bool,
+    ) -> Result<Bank, SplitError> {
+        let selected: Vec<bool> = self.users.iter().map(&mut pred).collect();
+        self.split_selected(name, credit_interest, debit_interest, &selected)
+    }
+
+    /// Moves the named users into a new bank, see [`Bank::split_off`]
+    pub fn split_users(
+        &mut self,
+        name: String,
+        credit_interest: u64,
+        debit_interest: u64,
+        names: &[&str],
+    ) -> Result<Bank, SplitError> {
+        let mut selected = vec![false; self.users.len()];
+        for &user in names {
+            let idx = self
+                .position(user)
+                .ok_or_else(|| SplitError::UserNotFound(user.to_string()))?;
+            selected[idx] = true;
+        }
+        self.split_selected(name, credit_interest, debit_interest, &selected)
+    }
+
+    fn split_selected(
+        &mut self,
+        name: String,
+        credit_interest: u64,
+        debit_interest: u64,
+        selected: &[bool],
+    ) -> Result<Bank, SplitError> {
+        let original = self.clone();
+        let before = self.exact_totals();
+        let mut bank = Bank::new(name, credit_interest, debit_interest);
+        bank.interest_schedule = self.interest_schedule;
+        let moving = self.users.iter().zip(selected).filter(|(_, s)| **s);
+        for (user, _) in moving {
+            let kind = EntryKind::Merge {
+                bank: self.name.clone(),
+                credit_line: user.credit_line,
+            };
+            let (status, limits, carry) = (user.status, user.limits, user.interest_carry);
+            let usage = user.usage.moved(self.today, bank.today);
+            let mut user = User::new(user.name.clone(), user.credit_line, user.balance);
+            user.usage = usage;
+            let balance = user.balance;
+            let idx = bank.insert_user(user);
+            bank.record_deposit(kind, idx, balance);
+            if carry != 0 {
+                // Recorded like an interest run that posted nothing, so
+                // replay restores it
+                bank.users[idx].interest_carry = carry;
+                bank.record(EntryKind::Interest { carry }, None, Some(idx), Money::ZERO);
+            }
+            if status != AccountStatus::Active {
+                bank.set_status(idx, status);
+            }
+            if limits != TransferLimits::default() {
+                bank.set_limits(idx, limits);
+            }
+        }
+
+        let moving_loans: Vec<Loan> = self
+            .loans
+            .iter()
+            .filter(|l| bank.position(&l.borrower).is_some())
+            .cloned()
+            .collect();
+        for loan in moving_loans {
+            bank.adopt_loan(loan, &self.name, self.today);
+        }
+
+        for idx in (0..self.users.len()).filter(|&idx| selected[idx]) {
+            let balance = self.users[idx].balance;
+            self.users[idx].balance = Money::ZERO;
+            let kind = EntryKind::Split {
+                bank: bank.name.clone(),
+            };
+            self.record_deposit(kind, idx, -balance);
+        }
+        let moved = |l: &&Loan| bank.position(&l.borrower).is_some();
+        for loan in self.loans.iter().filter(moved) {
+            if !loan.outstanding.is_zero() {
+                let kind = EntryKind::LoanTransfer {
+                    loan: loan.id,
+                    bank: bank.name.clone(),
+                    outstanding: -loan.outstanding,
+                };
+                self.ledger.push(kind, None, None, loan.outstanding);
+            }
+        }
+        self.loans.retain(|l| !moved(&l));
+        let mut selected = selected.iter();
+        self.users
+            .retain(|_| !selected.next().copied().unwrap_or_default());
+        self.reindex();
+
+        let (ours, theirs) = (self.exact_totals(), bank.exact_totals());
+        let after = (ours.0 + theirs.0, ours.1 + theirs.1);
+        if after != before {
+            *self = original;
+            return Err(SplitError::Unbalanced { before, after });
+        }
+        Ok(bank)
+    }
+
+    /// Liabilities and assets as in [`Bank::calc_balance`], in minor units
+    /// and without saturating
+    fn exact_totals(&self) -> (i128, i128) {
+        let mut liabilities = self.fee_revenue.minor() as i128;
+        let mut assets = 0;
+        for user in &self.users {
+            if user.balance.is_positive() {
+                liabilities += user.balance.minor() as i128;
+            } else {
+                assets -= user.balance.minor() as i128;
+            }
+        }
+        for loan in &self.loans {
+            assets += loan.outstanding.minor() as i128;
+        }
+        (liabilities, assets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::{AccountId, CompoundingPeriod, DayCount, InterestSchedule, testing};
+
+    fn sample_bank() -> Bank {
+        let users = [
+            ("Alice", 1000, 2000),
+            ("Bob", 1000, -500),
+            ("Carol", 1000, 700),
+            ("Dave", 1000, 0),
+        ];
+        testing::bank("Big Bank", (500, 1000), &users)
+    }
+
+    #[test]
+    fn test_split_off_by_predicate() {
+        let mut bank = sample_bank();
+        bank.transfer_funds("Alice", "Bob", 300.into()).unwrap();
+        let (liabilities, assets) = bank.calc_balance();
+
+        let spun = bank
+            .split_off("Small Bank".to_string(), 100, 200, |u| {
+                u.balance < Money::from_minor(1000)
+            })
+            .unwrap();
+
+        let names: Vec<_> = spun.users().iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, vec!["Bob", "Carol", "Dave"]);
+        assert_eq!(spun.debit_interest, 200);
+        assert_eq!(spun.user("Bob").unwrap().balance, Money::from_minor(-200));
+        assert_eq!(
+            spun.user("Bob").unwrap().credit_line,
+            Money::from_minor(1000)
+        );
+
+        assert_eq!(bank.users().len(), 1);
+        assert!(bank.user("Carol").is_none());
+        assert_eq!(bank.user("Alice").unwrap().id(), AccountId(1));
+
+        let (l1, a1) = bank.calc_balance();
+        let (l2, a2) = spun.calc_balance();
+        assert_eq!(l1.checked_add(l2), Some(liabilities));
+        assert_eq!(a1.checked_add(a2), Some(assets));
+    }
+
+    #[test]
+    fn test_split_keeps_unposted_interest() {
+        let mut bank = testing::bank("Big Bank", (500, 1000), &[("Alice", 0, 10)]);
+        bank.interest_schedule = Some(InterestSchedule::new(
+            CompoundingPeriod::Daily,
+            DayCount::Actual365,
+        ));
+        bank.accrue_interest().unwrap();
+        let carry = bank.users[0].interest_carry;
+        assert!(carry > 0);
+
+        let spun = bank
+            .split_users("New Bank".to_string(), 500, 1000, &["Alice"])
+            .unwrap();
+        assert_eq!(spun.users[0].interest_carry, carry);
+        assert_eq!(spun.users[0].balance, Money::from_minor(10));
+        testing::assert_replays(&spun);
+    }
+
+    #[test]
+    fn test_split_checks_exact_totals() {
+        // Both banks' totals saturate, but the exact sums still agree
+        let users = [
+            ("Alice", 0, i64::MAX),
+            ("Bob", 0, i64::MAX),
+            ("Carol", 0, 1),
+        ];
+        let mut bank = testing::bank("Big Bank", (0, 0), &users);
+        assert_eq!(bank.calc_balance().0, Money::MAX);
+        let spun = bank
+            .split_users("New Bank".to_string(), 0, 0, &["Bob"])
+            .unwrap();
+        assert_eq!(spun.calc_balance().0, Money::MAX);
+        assert_eq!(bank.users().len(), 2);
+    }
+
+    #[test]
+    fn test_split_users_by_name() {
+        let mut bank = sample_bank();
+        assert!(matches!(
+            bank.split_users("New Bank".to_string(), 0, 0, &["alice", "Zed"]),
+            Err(SplitError::UserNotFound(name)) if name == "Zed"
+        ));
+        assert_eq!(bank.users().len(), 4);
+
+        let spun = bank
+            .split_users("New Bank".to_string(), 0, 0, &["alice", "DAVE"])
+            .unwrap();
+        assert_eq!(spun.users().len(), 2);
+        assert_eq!(bank.users().len(), 2);
+        assert_eq!(bank.user("Carol").unwrap().id(), AccountId(3));
+        bank.transfer_funds("Carol", "Bob", 100.into()).unwrap();
+    }
+
+    #[test]
+    fn test_split_replays() {
+        let mut bank = sample_bank();
+        let spun = bank
+            .split_users("New Bank".to_string(), 0, 0, &["Bob"])
+            .unwrap();
+        bank.add_user(User::new("Bob".to_string(), 0.into(), 5.into()))
+            .unwrap();
+
+        testing::assert_replays(&bank);
+        testing::assert_replays(&spun);
+    }
+}
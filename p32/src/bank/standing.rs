@@ -0,0 +1,287 @@
+use std::error::Error;
+use std::fmt;
+
+use super::{Bank, FeeFailure, Money, TransferError};
+
+/// Identifier of a standing order within one bank
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OrderId(pub u64);
+
+impl fmt::Display for OrderId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+/// A recurring transfer executed as the bank's clock advances.
+///
+/// Runs on day `start` and then every `every` days. `remaining` limits the
+/// number of runs; `None` repeats until the order is cancelled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StandingOrder {
+    pub from: String,
+    pub to: String,
+    pub amount: Money,
+    pub every: u64, // days between runs
+    pub start: u64, // day of the first run
+    pub remaining: Option<u32>,
+    pub(super) id: OrderId, // assigned by the bank in add_standing_order
+}
+
+impl StandingOrder {
+    pub fn new(from: &str, to: &str, amount: Money, every: u64, start: u64) -> Self {
+        Self {
+            from: from.to_string(),
+            to: to.to_string(),
+            amount,
+            every,
+            start,
+            remaining: None,
+            id: OrderId::default(),
+        }
+    }
+
+    /// Limits the order to `count` runs
+    pub fn times(mut self, count: u32) -> Self {
+        self.remaining = Some(count);
+        self
+    }
+
+    pub fn id(&self) -> OrderId {
+        self.id
+    }
+
+    fn is_due(&self, day: u64) -> bool {
+        day >= self.start
+            && (day - self.start).is_multiple_of(self.every)
+            && self.remaining != Some(0)
+    }
+}
+
+/// Outcome of one run of a standing order
+#[derive(Debug)]
+pub struct OrderRun {
+    pub order: OrderId,
+    pub day: u64,
+    pub result: Result<(), TransferError>,
+}
+
+/// What happened while the clock advanced, in the order it happened
+#[derive(Debug, Default)]
+pub struct ClockReport {
+    pub runs: Vec<OrderRun>,
+    pub fee_failures: Vec<FeeFailure>,
+}
+
+#[derive(Debug)]
+pub enum OrderError {
+    ZeroPeriod,
+    NoRuns,
+    OrderNotFound(OrderId),
+    /// The order could never run, e.g. an unknown user or a zero amount
+    Invalid(TransferError),
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OrderError::ZeroPeriod => write!(f, "Standing orders must repeat at least daily"),
+            OrderError::NoRuns => write!(f, "Standing order would never run"),
+            OrderError::OrderNotFound(id) => write!(f, "Standing order {} not found", id),
+            OrderError::Invalid(err) => write!(f, "Invalid standing order: {}", err),
+        }
+    }
+}
+
+impl Error for OrderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OrderError::Invalid(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl Bank {
+    /// Current day of the bank's logical clock, starting at 0
+    pub fn today(&self) -> u64 {
+        self.today
+    }
+
+    pub fn standing_orders(&self) -> &[StandingOrder] {
+        &self.orders
+    }
+
+    /// Registers a standing order and returns its new id.
+    ///
+    /// Both users must exist, differ and not be closed and the amount must
+    /// be positive; the funds are only checked when the order runs.
+    pub fn add_standing_order(&mut self, mut order: StandingOrder) -> Result<OrderId, OrderError> {
+        if order.every == 0 {
+            return Err(OrderError::ZeroPeriod);
+        }
+        if order.remaining == Some(0) {
+            return Err(OrderError::NoRuns);
+        }
+        let from = self
+            .position(&order.from)
+            .ok_or_else(|| TransferError::UserNotFound(order.from.clone()));
+        let to = self
+            .position(&order.to)
+            .ok_or_else(|| TransferError::UserNotFound(order.to.clone()));
+        let invalid = match (from, to) {
+            (Err(err), _) | (_, Err(err)) => Some(err),
+            (Ok(from), Ok(to)) if from == to => {
+                Some(TransferError::SelfTransfer(order.from.clone()))
+            }
+            _ if order.amount.is_zero() => Some(TransferError::ZeroAmount),
+            _ if order.amount.is_negative() => Some(TransferError::NegativeAmount(order.amount)),
+            (Ok(from), Ok(to)) => {
+                let open = self.users[from].check_open();
+                open.and(self.users[to].check_open()).err()
+            }
+        };
+        if let Some(err) = invalid {
+            return Err(OrderError::Invalid(err));
+        }
+
+        order.id = OrderId(self.next_order_id);
+        self.next_order_id += 1;
+        self.orders.push(order);
+        Ok(OrderId(self.next_order_id - 1))
+    }
+
+    pub fn cancel_standing_order(&mut self, id: OrderId) -> Result<StandingOrder, OrderError> {
+        let idx = self
+            .orders
+            .iter()
+            .position(|o| o.id == id)
+            .ok_or(OrderError::OrderNotFound(id))?;
+        Ok(self.orders.remove(idx))
+    }
+
+    /// Moves the clock forward by `days`, running every order that falls due.
+    ///
+    /// Orders due on the same day run in the order they were added, through
+    /// [`Bank::transfer_funds`]. A failed run is reported with its error and
+    /// still counts towards the order's runs; it is not retried. Orders with
+    /// no runs left are removed. Loan installments due each day are then
+    /// collected, and maintenance fees charged last; fees that cannot be
+    /// charged are reported separately from the order runs.
+    pub fn advance_clock(&mut self, days: u64) -> ClockReport {
+        let mut report = ClockReport::default();
+        for _ in 0..days {
+            self.today += 1;
+            for idx in 0..self.orders.len() {
+                let order = &self.orders[idx];
+                if !order.is_due(self.today) {
+                    continue;
+                }
+                let (id, amount) = (order.id, order.amount);
+                let (from, to) = (order.from.clone(), order.to.clone());
+                report.runs.push(OrderRun {
+                    order: id,
+                    day: self.today,
+                    result: self.transfer_funds(&from, &to, amount),
+                });
+                if let Some(remaining) = &mut self.orders[idx].remaining {
+                    *remaining -= 1;
+                }
+            }
+            self.orders.retain(|o| o.remaining != Some(0));
+            self.collect_loan_installments();
+            let failures = self.charge_maintenance_fees();
+            report.fee_failures.extend(failures);
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::testing;
+
+    fn sample_bank() -> Bank {
+        testing::bank(
+            "Rust Bank",
+            (0, 0),
+            &[("Tenant", 0, 2500), ("Landlord", 0, 0)],
+        )
+    }
+
+    #[test]
+    fn test_orders_run_on_schedule() {
+        let mut bank = sample_bank();
+        let rent = StandingOrder::new("Tenant", "Landlord", 1000.into(), 30, 5);
+        let id = bank.add_standing_order(rent).unwrap();
+
+        assert!(bank.advance_clock(4).runs.is_empty());
+        let runs = bank.advance_clock(1).runs;
+        assert_eq!(runs.len(), 1);
+        assert_eq!((runs[0].order, runs[0].day), (id, 5));
+        assert!(runs[0].result.is_ok());
+
+        let runs = bank.advance_clock(60).runs;
+        let days: Vec<_> = runs.iter().map(|r| r.day).collect();
+        assert_eq!(days, vec![35, 65]);
+        assert_eq!(bank.today(), 65);
+
+        // The third payment bounces but the run carries on
+        assert!(matches!(
+            runs[1].result,
+            Err(TransferError::InsufficientFunds(_))
+        ));
+        assert_eq!(
+            bank.user("Landlord").unwrap().balance,
+            Money::from_minor(2000)
+        );
+        assert_eq!(bank.standing_orders().len(), 1);
+    }
+
+    #[test]
+    fn test_limited_and_cancelled_orders() {
+        let mut bank = sample_bank();
+        let savings = StandingOrder::new("Tenant", "Landlord", 100.into(), 7, 1).times(2);
+        bank.add_standing_order(savings).unwrap();
+        let daily = StandingOrder::new("Landlord", "Tenant", 1.into(), 1, 1);
+        let daily = bank.add_standing_order(daily).unwrap();
+
+        bank.advance_clock(3);
+        assert_eq!(bank.standing_orders().len(), 2);
+        assert_eq!(bank.cancel_standing_order(daily).unwrap().id(), daily);
+        assert!(matches!(
+            bank.cancel_standing_order(daily),
+            Err(OrderError::OrderNotFound(_))
+        ));
+
+        assert_eq!(bank.advance_clock(30).runs.len(), 1);
+        assert!(bank.standing_orders().is_empty());
+        assert_eq!(
+            bank.user("Tenant").unwrap().balance,
+            Money::from_minor(2303)
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_orders() {
+        let mut bank = sample_bank();
+        let cases = [
+            StandingOrder::new("Tenant", "Nobody", 1.into(), 1, 1),
+            StandingOrder::new("Tenant", "tenant", 1.into(), 1, 1),
+            StandingOrder::new("Tenant", "Landlord", 0.into(), 1, 1),
+        ];
+        for order in cases {
+            assert!(matches!(
+                bank.add_standing_order(order),
+                Err(OrderError::Invalid(_))
+            ));
+        }
+        let order = StandingOrder::new("Tenant", "Landlord", 1.into(), 0, 1);
+        assert!(matches!(
+            bank.add_standing_order(order),
+            Err(OrderError::ZeroPeriod)
+        ));
+        assert!(bank.standing_orders().is_empty());
+    }
+}
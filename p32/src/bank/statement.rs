@@ -0,0 +1,240 @@
+use std::fmt::Write;
+use std::ops::Range;
+
+use super::{Bank, EntryKind, LedgerEntry, Money};
+
+/// One movement on a user's account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementLine {
+    pub seq: u64,
+    pub kind: EntryKind,
+    pub description: String,
+    pub amount: Money,  // positive = paid in, negative = paid out
+    pub balance: Money, // running balance after this movement
+}
+
+/// Movements of one user over a range of ledger positions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    pub bank: String,
+    pub user: String,
+    pub range: Range<u64>,
+    pub opening_balance: Money,
+    pub lines: Vec<StatementLine>,
+    pub closing_balance: Money,
+}
+
+impl Bank {
+    /// Statement for the named user covering ledger entries in `range`.
+    ///
+    /// Returns `None` if the user does not exist.
+    pub fn statement(&self, name: &str, range: Range<u64>) -> Option<Statement> {
+        let name = &self.user(name)?.name;
+
+        let mut opening_balance = Money::ZERO;
+        let mut lines = Vec::new();
+        for entry in self.ledger.for_user(name) {
+            if entry.seq >= range.end {
+                break;
+            }
+            let (amount, balance) = movement(entry, name);
+            if entry.seq < range.start {
+                opening_balance = balance;
+            } else {
+                lines.push(StatementLine {
+                    seq: entry.seq,
+                    kind: entry.kind.clone(),
+                    description: describe(entry, name),
+                    amount,
+                    balance,
+                });
+            }
+        }
+
+        let closing_balance = lines.last().map_or(opening_balance, |l| l.balance);
+        Some(Statement {
+            bank: self.name.clone(),
+            user: name.clone(),
+            range,
+            opening_balance,
+            lines,
+            closing_balance,
+        })
+    }
+}
+
+impl Statement {
+    /// Plain-text rendering for sending to a customer
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Statement for {} at {}", self.user, self.bank);
+        let _ = writeln!(
+            out,
+            "Ledger entries {} to {}",
+            self.range.start, self.range.end
+        );
+        let _ = writeln!(out, "{:<46}{:>16}", "Opening balance", self.opening_balance);
+        for line in &self.lines {
+            let _ = writeln!(
+                out,
+                "{:>6}  {:<28}{:>14}{:>16}",
+                line.seq, line.description, line.amount, line.balance
+            );
+        }
+        let _ = writeln!(out, "{:<46}{:>16}", "Closing balance", self.closing_balance);
+        out
+    }
+
+    /// CSV with a header row, one row per movement and opening/closing rows
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("seq,description,amount,balance\n");
+        let _ = writeln!(out, ",opening balance,,{}", self.opening_balance);
+        for line in &self.lines {
+            let _ = writeln!(
+                out,
+                "{},{},{},{}",
+                line.seq,
+                csv_field(&line.description),
+                line.amount,
+                line.balance
+            );
+        }
+        let _ = writeln!(out, ",closing balance,,{}", self.closing_balance);
+        out
+    }
+}
+
+/// Signed amount for `name` and its balance after the entry
+fn movement(entry: &LedgerEntry, name: &str) -> (Money, Money) {
+    if entry.from.as_deref() == Some(name) {
+        (-entry.amount, entry.from_balance.unwrap_or_default())
+    } else {
+        (entry.amount, entry.to_balance.unwrap_or_default())
+    }
+}
+
+fn describe(entry: &LedgerEntry, name: &str) -> String {
+    match &entry.kind {
+        EntryKind::Open { .. } => "opening deposit".to_string(),
+        EntryKind::Interest { .. } => "interest".to_string(),
+        EntryKind::Merge { bank, .. } => format!("merged from {}", bank),
+        EntryKind::Split { bank } => format!("moved to {}", bank),
+        EntryKind::Fee { trigger } => format!("{} fee", trigger),
+        EntryKind::CreditLine { credit_line } => format!("credit line set to {}", credit_line),
+        EntryKind::Clearing { bank, user } => {
+            if entry.from.as_deref() == Some(name) {
+                format!("payment to {} at {}", user, bank)
+            } else {
+                format!("payment from {} at {}", user, bank)
+            }
+        }
+        EntryKind::Status { status } => format!("account {}", status),
+        EntryKind::Limits { .. } => "transfer limits changed".to_string(),
+        EntryKind::Settlement { house, .. } => format!("settled by {}", house),
+        EntryKind::LoanDisbursement { loan } => format!("loan {} disbursed", loan),
+        EntryKind::LoanInterest { loan } => format!("loan {} interest", loan),
+        EntryKind::LoanPrincipal { loan } => format!("loan {} repayment", loan),
+        EntryKind::LoanTransfer { loan, bank, .. } => format!("loan {} moved with {}", loan, bank),
+        EntryKind::Transfer => {
+            if entry.from.as_deref() == Some(name) {
+                format!("transfer to {}", entry.to.as_deref().unwrap_or_default())
+            } else {
+                format!(
+                    "transfer from {}",
+                    entry.from.as_deref().unwrap_or_default()
+                )
+            }
+        }
+    }
+}
+
+pub(super) fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::{User, testing};
+
+    fn sample_bank() -> Bank {
+        let users = [("Alice", 0, 2000), ("Bob, Jr.", 0, 0)];
+        let mut bank = testing::bank("Rust Bank", (0, 1000), &users);
+        bank.transfer_funds("Alice", "Bob, Jr.", 500.into())
+            .unwrap();
+        bank.accrue_interest().unwrap();
+        bank.transfer_funds("Bob, Jr.", "Alice", 50.into()).unwrap();
+        bank
+    }
+
+    #[test]
+    fn test_full_statement() {
+        let bank = sample_bank();
+        let statement = bank.statement("alice", 0..u64::MAX).unwrap();
+
+        assert_eq!(statement.user, "Alice");
+        assert_eq!(statement.opening_balance, Money::ZERO);
+        let amounts: Vec<_> = statement.lines.iter().map(|l| l.amount.minor()).collect();
+        assert_eq!(amounts, vec![2000, -500, 150, 50]);
+        assert_eq!(statement.closing_balance, Money::from_minor(1700));
+        assert_eq!(
+            statement.closing_balance,
+            bank.user("Alice").unwrap().balance
+        );
+    }
+
+    #[test]
+    fn test_statement_range() {
+        let bank = sample_bank();
+        let statement = bank.statement("Alice", 3..5).unwrap();
+        assert_eq!(statement.opening_balance, Money::from_minor(1500));
+        assert_eq!(statement.lines.len(), 1);
+        assert_eq!(statement.lines[0].description, "interest");
+        assert_eq!(statement.closing_balance, Money::from_minor(1650));
+
+        let empty = bank.statement("Alice", 6..10).unwrap();
+        assert!(empty.lines.is_empty());
+        assert_eq!(empty.opening_balance, empty.closing_balance);
+        assert!(bank.statement("Carol", 0..10).is_none());
+    }
+
+    #[test]
+    fn test_reused_name_starts_a_new_history() {
+        let mut bank = sample_bank();
+        assert_eq!(bank.ledger().for_user(" ALICE ").count(), 4);
+
+        bank.split_users("Other Bank".to_string(), 0, 0, &["Alice"])
+            .unwrap();
+        assert_eq!(bank.ledger().for_user("alice").count(), 0);
+        bank.add_user(User::new("ALICE".to_string(), 0.into(), 30.into()))
+            .unwrap();
+
+        let statement = bank.statement("alice", 0..u64::MAX).unwrap();
+        assert_eq!(statement.user, "ALICE");
+        assert_eq!(statement.lines.len(), 1);
+        assert_eq!(statement.lines[0].description, "opening deposit");
+        assert_eq!(statement.closing_balance, Money::from_minor(30));
+    }
+
+    #[test]
+    fn test_renderings() {
+        let bank = sample_bank();
+        let statement = bank.statement("Alice", 0..u64::MAX).unwrap();
+
+        let text = statement.to_text();
+        assert!(text.starts_with("Statement for Alice at Rust Bank\n"));
+        assert!(text.contains("transfer to Bob, Jr."));
+        assert!(text.ends_with(&format!("{:<46}{:>16}\n", "Closing balance", "17.00")));
+
+        let csv = statement.to_csv();
+        let rows: Vec<_> = csv.lines().collect();
+        assert_eq!(rows[0], "seq,description,amount,balance");
+        assert_eq!(rows[1], ",opening balance,,0.00");
+        assert_eq!(rows[3], "2,\"transfer to Bob, Jr.\",-5.00,15.00");
+        assert_eq!(rows.last(), Some(&",closing balance,,17.00"));
+    }
+}
@@ -0,0 +1,203 @@
+use std::fmt;
+
+use super::{AccountError, Bank, EntryKind, Money, normalize_name};
+
+/// Lifecycle state of an account.
+///
+/// Accounts open as `Active`. A `Frozen` account can still receive money
+/// but cannot send any; a `Closed` account can do neither and never reopens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AccountStatus {
+    #[default]
+    Active,
+    Frozen,
+    Closed,
+}
+
+impl fmt::Display for AccountStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccountStatus::Active => write!(f, "active"),
+            AccountStatus::Frozen => write!(f, "frozen"),
+            AccountStatus::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+impl Bank {
+    /// Blocks all payments out of an active account
+    pub fn freeze(&mut self, name: &str) -> Result<(), AccountError> {
+        self.change_status(name, AccountStatus::Active, AccountStatus::Frozen)
+    }
+
+    /// Lets a frozen account send money again
+    pub fn unfreeze(&mut self, name: &str) -> Result<(), AccountError> {
+        self.change_status(name, AccountStatus::Frozen, AccountStatus::Active)
+    }
+
+    /// Closes an active or frozen account with a zero balance and no loan
+    /// outstanding.
+    ///
+    /// Any unposted interest carry is forfeited. The name stays taken, so a
+    /// closed account cannot be replaced by a new one of the same name.
+    pub fn close_account(&mut self, name: &str) -> Result<(), AccountError> {
+        let idx = self
+            .position(name)
+            .ok_or_else(|| AccountError::UserNotFound(name.to_string()))?;
+        let user = &self.users[idx];
+        if user.status == AccountStatus::Closed {
+            return Err(AccountError::InvalidTransition {
+                name: user.name.clone(),
+                from: user.status,
+                to: AccountStatus::Closed,
+            });
+        }
+        if !user.balance.is_zero() {
+            return Err(AccountError::BalanceNotZero(user.name.clone()));
+        }
+        let borrower = normalize_name(&user.name);
+        if self
+            .loans
+            .iter()
+            .any(|l| !l.is_repaid() && normalize_name(&l.borrower) == borrower)
+        {
+            return Err(AccountError::LoanOutstanding(user.name.clone()));
+        }
+        self.set_status(idx, AccountStatus::Closed);
+        Ok(())
+    }
+
+    fn change_status(
+        &mut self,
+        name: &str,
+        from: AccountStatus,
+        to: AccountStatus,
+    ) -> Result<(), AccountError> {
+        let idx = self
+            .position(name)
+            .ok_or_else(|| AccountError::UserNotFound(name.to_string()))?;
+        let user = &self.users[idx];
+        if user.status != from {
+            return Err(AccountError::InvalidTransition {
+                name: user.name.clone(),
+                from: user.status,
+                to,
+            });
+        }
+        self.set_status(idx, to);
+        Ok(())
+    }
+
+    /// Sets and records a status; closing also drops the interest carry
+    pub(super) fn set_status(&mut self, idx: usize, status: AccountStatus) {
+        let user = &mut self.users[idx];
+        user.status = status;
+        if status == AccountStatus::Closed {
+            user.interest_carry = 0;
+        }
+        self.record(EntryKind::Status { status }, None, Some(idx), Money::ZERO);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::{MergePolicy, TransferError, User, testing};
+
+    fn sample_bank() -> Bank {
+        testing::bank(
+            "Rust Bank",
+            (1000, 1000),
+            &[("Alice", 0, 500), ("Bob", 0, 100)],
+        )
+    }
+
+    #[test]
+    fn test_frozen_account_only_receives() {
+        let mut bank = sample_bank();
+        bank.freeze("alice").unwrap();
+        assert_eq!(bank.user("Alice").unwrap().status(), AccountStatus::Frozen);
+        assert!(matches!(
+            bank.freeze("Alice"),
+            Err(AccountError::InvalidTransition { .. })
+        ));
+        assert!(matches!(
+            bank.transfer_funds("Alice", "Bob", 1.into()),
+            Err(TransferError::AccountFrozen(name)) if name == "Alice"
+        ));
+        bank.transfer_funds("Bob", "Alice", 100.into()).unwrap();
+
+        // Interest still accrues on frozen accounts
+        bank.accrue_interest().unwrap();
+        assert_eq!(
+            bank.user("Alice").unwrap().balance(),
+            Money::from_minor(660)
+        );
+
+        bank.unfreeze("Alice").unwrap();
+        bank.transfer_funds("Alice", "Bob", 660.into()).unwrap();
+        assert!(matches!(
+            bank.unfreeze("Alice"),
+            Err(AccountError::InvalidTransition { .. })
+        ));
+    }
+
+    #[test]
+    fn test_close_account() {
+        let mut bank = sample_bank();
+        assert!(matches!(
+            bank.close_account("Alice"),
+            Err(AccountError::BalanceNotZero(_))
+        ));
+        bank.transfer_funds("Bob", "Alice", 100.into()).unwrap();
+        bank.freeze("Bob").unwrap();
+        bank.close_account("Bob").unwrap();
+
+        assert!(matches!(
+            bank.transfer_funds("Alice", "Bob", 1.into()),
+            Err(TransferError::AccountClosed(name)) if name == "Bob"
+        ));
+        assert!(matches!(
+            bank.set_credit_line("Bob", 100.into()),
+            Err(AccountError::AccountClosed(_))
+        ));
+        assert!(matches!(
+            bank.unfreeze("Bob"),
+            Err(AccountError::InvalidTransition { .. })
+        ));
+        assert!(matches!(
+            bank.add_user(User::new("Bob".to_string(), 0.into(), 0.into())),
+            Err(AccountError::DuplicateUser(_))
+        ));
+        assert!(bank.verify().is_empty());
+
+        testing::assert_replays(&bank);
+    }
+
+    #[test]
+    fn test_merge_respects_status() {
+        let mut bank = sample_bank();
+        bank.transfer_funds("Bob", "Alice", 100.into()).unwrap();
+        bank.close_account("Bob").unwrap();
+
+        let mut other = testing::bank("Other", (0, 0), &[("Carol", 0, 10), ("Bob", 0, 10)]);
+        other.freeze("Carol").unwrap();
+
+        let before = bank.clone();
+        assert!(matches!(
+            bank.merge_bank(other.clone()),
+            Err(TransferError::AccountClosed(name)) if name == "Bob"
+        ));
+        assert_eq!(bank, before);
+
+        bank.merge_bank_with(other, MergePolicy::RenameOnConflict)
+            .unwrap();
+        assert_eq!(bank.user("Carol").unwrap().status(), AccountStatus::Frozen);
+        assert_eq!(
+            bank.user("Bob (Other)").unwrap().status(),
+            AccountStatus::Active
+        );
+
+        testing::assert_replays(&bank);
+    }
+}
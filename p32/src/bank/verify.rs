@@ -0,0 +1,392 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::interest::CARRY_SCALE;
+use super::{
+    Account, AccountId, AccountStatus, Bank, InternalAccount, LoanId, Money, OrderId, TrialBalance,
+    normalize_name,
+};
+
+/// An invariant that does not hold, found by [`Bank::verify`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    NegativeCreditLine {
+        name: String,
+        credit_line: Money,
+    },
+    /// Overdrawn past the credit line. Interest and merges that lower a
+    /// credit line can cause this, so it marks an account for review
+    BeyondCreditLine {
+        name: String,
+        balance: Money,
+        credit_line: Money,
+    },
+    DuplicateName {
+        name: String,
+    },
+    DuplicateId {
+        id: AccountId,
+    },
+    /// Id was never handed out by this bank
+    UnknownId {
+        name: String,
+        id: AccountId,
+    },
+    /// The name or id index does not point at the right user
+    StaleIndex {
+        name: String,
+    },
+    InvalidCarry {
+        name: String,
+        carry: i64,
+    },
+    ClosedWithBalance {
+        name: String,
+        balance: Money,
+    },
+    /// Ledger entry at `position` is numbered `seq`
+    LedgerSequence {
+        position: usize,
+        seq: u64,
+    },
+    /// Balance differs from the sum of the user's ledger postings
+    LedgerBalance {
+        name: String,
+        ledger: i128,
+        balance: Money,
+    },
+    FeeRevenue {
+        ledger: i128,
+        recorded: Money,
+    },
+    UnbalancedBooks {
+        debits: i128,
+        credits: i128,
+    },
+    /// Settlement account not closed at the end of a clearing cycle
+    OpenSettlement {
+        balance: i128,
+    },
+    /// Standing order that can never run again
+    InvalidOrder {
+        id: OrderId,
+    },
+    /// Loans outstanding differ from the ledger's loans account
+    LoanBalance {
+        ledger: i128,
+        outstanding: Money,
+    },
+    /// Outstanding principal differs from the unpaid installments, or the
+    /// borrower does not exist
+    InvalidLoan {
+        id: LoanId,
+    },
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::NegativeCreditLine { name, credit_line } => {
+                write!(f, "{} has a negative credit line of {}", name, credit_line)
+            }
+            Violation::BeyondCreditLine {
+                name,
+                balance,
+                credit_line,
+            } => write!(
+                f,
+                "{} has balance {} beyond credit line {}",
+                name, balance, credit_line
+            ),
+            Violation::DuplicateName { name } => write!(f, "User {} exists more than once", name),
+            Violation::DuplicateId { id } => write!(f, "Account id {} is used twice", id),
+            Violation::UnknownId { name, id } => {
+                write!(f, "{} has account id {} that was never assigned", name, id)
+            }
+            Violation::StaleIndex { name } => write!(f, "Index entry for {} is stale", name),
+            Violation::InvalidCarry { name, carry } => {
+                write!(f, "{} has invalid interest carry {}", name, carry)
+            }
+            Violation::ClosedWithBalance { name, balance } => {
+                write!(f, "Closed account of {} has balance {}", name, balance)
+            }
+            Violation::LedgerSequence { position, seq } => {
+                write!(f, "Ledger entry {} is numbered {}", position, seq)
+            }
+            Violation::LedgerBalance {
+                name,
+                ledger,
+                balance,
+            } => write!(
+                f,
+                "{} has balance {} but the ledger says {} minor units",
+                name, balance, ledger
+            ),
+            Violation::FeeRevenue { ledger, recorded } => write!(
+                f,
+                "Fee revenue is {} but the ledger says {} minor units",
+                recorded, ledger
+            ),
+            Violation::UnbalancedBooks { debits, credits } => {
+                write!(f, "Debits {} do not equal credits {}", debits, credits)
+            }
+            Violation::OpenSettlement { balance } => write!(
+                f,
+                "Settlement account holds {} minor units after clearing",
+                balance
+            ),
+            Violation::InvalidOrder { id } => write!(f, "Standing order {} can never run", id),
+            Violation::LoanBalance {
+                ledger,
+                outstanding,
+            } => write!(
+                f,
+                "Loans outstanding are {} but the ledger says {} minor units",
+                outstanding, ledger
+            ),
+            Violation::InvalidLoan { id } => write!(f, "Loan {} does not match its schedule", id),
+        }
+    }
+}
+
+impl Bank {
+    /// Checks every invariant of the bank; an empty list means it is sound.
+    ///
+    /// Covers credit lines, unique names and ids, the lookup indexes,
+    /// interest carry, closed accounts, standing orders, loan schedules and
+    /// that balances, fee revenue, loans and the settlement account agree
+    /// with the ledger's double-entry books.
+    pub fn verify(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+
+        let mut names = HashMap::new();
+        let mut ids = HashMap::new();
+        for (idx, user) in self.users.iter().enumerate() {
+            let name = user.name.clone();
+            if user.credit_line.is_negative() {
+                violations.push(Violation::NegativeCreditLine {
+                    name: name.clone(),
+                    credit_line: user.credit_line,
+                });
+            } else if user.balance < -user.credit_line {
+                violations.push(Violation::BeyondCreditLine {
+                    name: name.clone(),
+                    balance: user.balance,
+                    credit_line: user.credit_line,
+                });
+            }
+            if names.insert(normalize_name(&name), idx).is_some() {
+                violations.push(Violation::DuplicateName { name: name.clone() });
+            }
+            if ids.insert(user.id, idx).is_some() {
+                violations.push(Violation::DuplicateId { id: user.id });
+            }
+            if user.id.0 == 0 || user.id.0 >= self.next_id {
+                violations.push(Violation::UnknownId {
+                    name: name.clone(),
+                    id: user.id,
+                });
+            }
+            if !(0..CARRY_SCALE).contains(&user.interest_carry) {
+                violations.push(Violation::InvalidCarry {
+                    name: name.clone(),
+                    carry: user.interest_carry,
+                });
+            }
+            if user.status == AccountStatus::Closed && !user.balance.is_zero() {
+                violations.push(Violation::ClosedWithBalance {
+                    name: name.clone(),
+                    balance: user.balance,
+                });
+            }
+            if self.position(&name) != Some(idx) || self.by_id.get(&user.id) != Some(&idx) {
+                violations.push(Violation::StaleIndex { name });
+            }
+        }
+        for (key, &idx) in &self.by_name {
+            let points_at = self.users.get(idx).map(|u| normalize_name(&u.name));
+            if points_at.as_ref() != Some(key) {
+                violations.push(Violation::StaleIndex { name: key.clone() });
+            }
+        }
+
+        for (position, entry) in self.ledger.iter().enumerate() {
+            if entry.seq != position as u64 {
+                violations.push(Violation::LedgerSequence {
+                    position,
+                    seq: entry.seq,
+                });
+            }
+        }
+
+        let trial = self.trial_balance();
+        violations.extend(unbalanced_books(&trial));
+        for line in trial.lines.iter().filter(|l| !l.reconciles()) {
+            let (ledger, recorded) = (line.balance(), line.recorded.unwrap_or(0));
+            violations.push(match &line.account {
+                // Recorded from the user's balance, so it fits
+                Account::User(name) => Violation::LedgerBalance {
+                    name: name.clone(),
+                    ledger,
+                    balance: Money::from_minor(recorded as i64),
+                },
+                Account::Internal(InternalAccount::FeeRevenue) => Violation::FeeRevenue {
+                    ledger,
+                    recorded: self.fee_revenue,
+                },
+                // The loans account is the only other one with a figure
+                Account::Internal(_) => Violation::LoanBalance {
+                    ledger,
+                    outstanding: self.loans_outstanding(),
+                },
+            });
+        }
+        let account = Account::Internal(InternalAccount::Settlement);
+        let balance = trial.line(&account).map_or(0, |l| l.balance());
+        if balance != 0 {
+            violations.push(Violation::OpenSettlement { balance });
+        }
+
+        for order in &self.orders {
+            if order.every == 0 || order.remaining == Some(0) {
+                violations.push(Violation::InvalidOrder { id: order.id() });
+            }
+        }
+        for loan in &self.loans {
+            let unpaid: i128 = loan
+                .installments
+                .iter()
+                .filter(|i| !i.paid)
+                .map(|i| i.principal.minor() as i128)
+                .sum();
+            if unpaid != loan.outstanding.minor() as i128 || self.position(&loan.borrower).is_none()
+            {
+                violations.push(Violation::InvalidLoan { id: loan.id() });
+            }
+        }
+        violations
+    }
+}
+
+/// Violation if the books' debits and credits differ
+fn unbalanced_books(trial: &TrialBalance) -> Option<Violation> {
+    (trial.total_debits != trial.total_credits).then_some(Violation::UnbalancedBooks {
+        debits: trial.total_debits,
+        credits: trial.total_credits,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bank::{AccountError, Fee, FeeRule, FeeTrigger, User, testing};
+
+    fn busy_bank() -> Bank {
+        let users = [("Alice", 500, 2000), ("Bob", 1000, -300), ("Carol", 0, 0)];
+        let mut bank = testing::bank("Sound Bank", (200, 400), &users);
+        bank.fees = vec![FeeRule::new(FeeTrigger::Transfer, Fee::Flat(3.into()))];
+        bank.transfer_funds("Alice", "Carol", 700.into()).unwrap();
+        bank.accrue_interest().unwrap();
+        bank.set_credit_line("Carol", 100.into()).unwrap();
+        bank.split_users("Spun".to_string(), 0, 0, &["Bob"])
+            .unwrap();
+        bank
+    }
+
+    #[test]
+    fn test_sound_bank_has_no_violations() {
+        let bank = busy_bank();
+        assert_eq!(bank.verify(), vec![]);
+
+        let mut rebuilt = Bank::new("Sound Bank".to_string(), 200, 400);
+        rebuilt.fees = bank.fees.clone();
+        rebuilt.replay(bank.ledger()).unwrap();
+        assert_eq!(rebuilt.user("Carol").unwrap().credit_line(), 100.into());
+        assert_eq!(rebuilt, bank);
+    }
+
+    #[test]
+    fn test_detects_corruption() {
+        let mut bank = busy_bank();
+        bank.users[0].balance = Money::from_minor(-900);
+        bank.users[1].credit_line = Money::from_minor(-1);
+        bank.users[1].name = "ALICE".to_string();
+        bank.users[1].interest_carry = -5;
+        bank.fee_revenue = Money::ZERO;
+
+        let violations = bank.verify();
+        let expected = [
+            Violation::BeyondCreditLine {
+                name: "Alice".to_string(),
+                balance: Money::from_minor(-900),
+                credit_line: Money::from_minor(500),
+            },
+            Violation::NegativeCreditLine {
+                name: "ALICE".to_string(),
+                credit_line: Money::from_minor(-1),
+            },
+            Violation::DuplicateName {
+                name: "ALICE".to_string(),
+            },
+            Violation::InvalidCarry {
+                name: "ALICE".to_string(),
+                carry: -5,
+            },
+            Violation::StaleIndex {
+                name: "ALICE".to_string(),
+            },
+            Violation::FeeRevenue {
+                ledger: 3,
+                recorded: Money::ZERO,
+            },
+        ];
+        for violation in &expected {
+            assert!(violations.contains(violation), "missing {:?}", violation);
+        }
+        assert!(violations.iter().any(|v| matches!(
+            v,
+            Violation::LedgerBalance { name, .. } if name == "Alice"
+        )));
+    }
+
+    #[test]
+    fn test_detects_unbalanced_books() {
+        let trial = busy_bank().trial_balance();
+        assert!(trial.is_balanced());
+        assert_eq!(unbalanced_books(&trial), None);
+
+        // An opening deposit credited to Alice with no matching debit
+        let mut lines = trial.lines;
+        let alice = Account::User("Alice".to_string());
+        let line = lines.iter_mut().find(|l| l.account == alice).unwrap();
+        line.credits += 500;
+        let trial = TrialBalance::from_lines(lines);
+        assert_eq!(trial.total_credits, trial.total_debits + 500);
+        assert_eq!(
+            unbalanced_books(&trial),
+            Some(Violation::UnbalancedBooks {
+                debits: trial.total_debits,
+                credits: trial.total_credits,
+            })
+        );
+    }
+
+    #[test]
+    fn test_credit_line_validation() {
+        let mut bank = busy_bank();
+        assert!(matches!(
+            bank.set_credit_line("Carol", (-1).into()),
+            Err(AccountError::NegativeCreditLine(_))
+        ));
+        bank.transfer_funds("Carol", "Alice", 750.into()).unwrap();
+        assert!(matches!(
+            bank.set_credit_line("Carol", 0.into()),
+            Err(AccountError::BeyondCreditLine(_))
+        ));
+        assert!(matches!(
+            bank.add_user(User::new("Dave".to_string(), 10.into(), (-20).into())),
+            Err(AccountError::BeyondCreditLine(_))
+        ));
+        assert!(bank.verify().is_empty());
+    }
+}
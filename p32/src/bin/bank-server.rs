@@ -0,0 +1,455 @@
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::process;
+use std::time::Duration;
+
+use p32::bank::{Bank, Money, TransferError, User};
+
+const USAGE: &str = "\
+Usage: bank-server <file> [address]
+
+Serves the bank file over HTTP with JSON bodies, by default on
+127.0.0.1:8080. Use port 0 to pick a free port; the address actually
+bound is printed on the first line of output.
+
+Endpoints:
+  GET  /users           list users
+  GET  /users/<name>    one user, name percent-encoded
+  POST /transfers       {\"from\": ..., \"to\": ..., \"amount\": \"12.50\"}
+  POST /interest        accrue one period of interest
+  GET  /balance         liabilities, assets and net position
+
+Amounts are strings in major units. Failed requests answer with
+{\"error\": <kind>, \"message\": <text>}.
+
+The server does not authenticate callers: anyone who can reach the
+address can move money between any accounts. Only bind it to an
+address that trusted clients alone can reach.";
+
+/// Largest request line plus headers accepted, in bytes
+const MAX_HEAD: u64 = 8 * 1024;
+
+/// Largest request body accepted, in bytes
+const MAX_BODY: usize = 64 * 1024;
+
+/// How long a client may take to send a request or read the response
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (path, addr) = match args.as_slice() {
+        [path] => (path, "127.0.0.1:8080"),
+        [path, addr] => (path, addr.as_str()),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(err) = serve(Path::new(path), addr) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
+
+/// Answers requests one at a time, saving the bank after every change.
+///
+/// Requests are not authenticated; see [`USAGE`].
+///
+/// Changes are made to a copy of the bank that replaces it only once saved,
+/// so a failed save leaves the served state as it is on disk.
+fn serve(path: &Path, addr: &str) -> Result<(), Box<dyn Error>> {
+    let mut bank = Bank::load(path)?;
+    let listener = TcpListener::bind(addr)?;
+    println!("Listening on http://{}", listener.local_addr()?);
+    io::stdout().flush()?;
+
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                continue;
+            }
+        };
+        let timeouts = stream
+            .set_read_timeout(Some(TIMEOUT))
+            .and_then(|()| stream.set_write_timeout(Some(TIMEOUT)));
+        if let Err(err) = timeouts {
+            eprintln!("error: {}", err);
+            continue;
+        }
+        let response = match read_request(&mut stream) {
+            Ok(request) => {
+                let mut next = bank.clone();
+                let response = handle(&mut next, &request);
+                if next == bank {
+                    response
+                } else if let Err(err) = next.save(path) {
+                    Response::error(500, "save-failed", &err.to_string())
+                } else {
+                    bank = next;
+                    response
+                }
+            }
+            Err(response) => response,
+        };
+        if let Err(err) = response.write_to(&mut stream) {
+            eprintln!("error: {}", err);
+        }
+    }
+    Ok(())
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: String,
+}
+
+/// Reads one HTTP/1.1 request; only `Content-Length` bodies are supported.
+///
+/// Fails with the response to send, 431 if the request line and headers
+/// exceed [`MAX_HEAD`] and 400 for anything else that is malformed.
+fn read_request(stream: &mut TcpStream) -> Result<Request, Response> {
+    let bad = |message: &str| Response::error(400, "bad-request", message);
+    let mut reader = BufReader::new(stream.take(MAX_HEAD));
+    let line = read_head_line(&mut reader)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(bad("malformed request line"));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut length = 0;
+    loop {
+        let header = read_head_line(&mut reader)?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value
+                .trim()
+                .parse()
+                .map_err(|_| bad("invalid Content-Length"))?;
+        }
+    }
+    if length > MAX_BODY {
+        return Err(bad(&format!("body larger than {} bytes", MAX_BODY)));
+    }
+    reader.get_mut().set_limit(length as u64);
+    let mut body = vec![0; length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|e| bad(&e.to_string()))?;
+    let body = String::from_utf8(body).map_err(|_| bad("body is not UTF-8"))?;
+    Ok(Request { method, path, body })
+}
+
+/// Reads one line of the request head, failing once [`MAX_HEAD`] is used up
+fn read_head_line(reader: &mut BufReader<io::Take<&mut TcpStream>>) -> Result<String, Response> {
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .map_err(|e| Response::error(400, "bad-request", &e.to_string()))?;
+    if !line.ends_with('\n') && reader.get_ref().limit() == 0 {
+        let message = format!("request head larger than {} bytes", MAX_HEAD);
+        return Err(Response::error(431, "head-too-large", &message));
+    }
+    Ok(line)
+}
+
+struct Response {
+    status: u16,
+    body: String,
+}
+
+impl Response {
+    fn ok(body: String) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, kind: &str, message: &str) -> Self {
+        let body = format!(
+            "{{\"error\":{},\"message\":{}}}",
+            json::string(kind),
+            json::string(message)
+        );
+        Self { status, body }
+    }
+
+    fn write_to(&self, stream: &mut TcpStream) -> io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            410 => "Gone",
+            422 => "Unprocessable Entity",
+            423 => "Locked",
+            431 => "Request Header Fields Too Large",
+            _ => "Internal Server Error",
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason,
+            self.body.len(),
+            self.body
+        )?;
+        stream.flush()
+    }
+}
+
+impl From<TransferError> for Response {
+    fn from(err: TransferError) -> Self {
+        let status = match err {
+            TransferError::UserNotFound(_) => 404,
+            TransferError::ZeroAmount
+            | TransferError::NegativeAmount(_)
+            | TransferError::SelfTransfer(_) => 400,
+            TransferError::InsufficientFunds(_)
+            | TransferError::CreditLimitExceeded(_)
+            | TransferError::ArithmeticOverflow(_)
+            | TransferError::LimitExceeded { .. } => 422,
+            TransferError::AccountFrozen(_) => 423,
+            TransferError::AccountClosed(_) => 410,
+            // Only raised by SecuredBank, which this server does not use
+            TransferError::Unauthorized(_) => 403,
+            TransferError::UnknownLogin(_) => 404,
+        };
+        Response::error(status, err.kind(), &err.to_string())
+    }
+}
+
+fn handle(bank: &mut Bank, request: &Request) -> Response {
+    let route = request.path.split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = route.trim_matches('/').split('/').collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["users"]) => {
+            let users: Vec<String> = bank.users().iter().map(user_json).collect();
+            Response::ok(format!("{{\"users\":[{}]}}", users.join(",")))
+        }
+        ("GET", ["users", name]) => {
+            let Some(name) = percent_decode(name) else {
+                return Response::error(400, "bad-request", "invalid percent-encoding");
+            };
+            match bank.user(&name) {
+                Some(user) => Response::ok(user_json(user)),
+                None => TransferError::UserNotFound(name).into(),
+            }
+        }
+        ("POST", ["transfers"]) => transfer(bank, &request.body).unwrap_or_else(|err| err),
+        ("POST", ["interest"]) => match bank.accrue_interest() {
+            Ok(()) => Response::ok(balance_json(bank)),
+            Err(err) => err.into(),
+        },
+        ("GET", ["balance"]) => Response::ok(balance_json(bank)),
+        (_, ["users"] | ["users", _] | ["transfers"] | ["interest"] | ["balance"]) => {
+            let message = format!("{} is not allowed on {}", request.method, route);
+            Response::error(405, "method-not-allowed", &message)
+        }
+        _ => Response::error(404, "not-found", &format!("no endpoint at {}", route)),
+    }
+}
+
+fn transfer(bank: &mut Bank, body: &str) -> Result<Response, Response> {
+    let bad = |message: String| Response::error(400, "bad-request", &message);
+    let fields = json::parse_object(body).map_err(bad)?;
+    let field = |key: &str| {
+        fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+            .ok_or_else(|| bad(format!("missing field {:?}", key)))
+    };
+    let (from, to) = (field("from")?, field("to")?);
+    let amount: Money = field("amount")?
+        .parse()
+        .map_err(|err| bad(format!("{}", err)))?;
+    bank.transfer_funds(from, to, amount)?;
+    Ok(Response::ok(format!(
+        "{{\"from\":{},\"to\":{},\"amount\":{}}}",
+        json::string(from),
+        json::string(to),
+        json::string(&amount.to_string())
+    )))
+}
+
+fn user_json(user: &User) -> String {
+    format!(
+        "{{\"id\":{},\"name\":{},\"balance\":{},\"credit_line\":{},\"status\":{}}}",
+        user.id().0,
+        json::string(user.name()),
+        json::string(&user.balance().to_string()),
+        json::string(&user.credit_line().to_string()),
+        json::string(&user.status().to_string())
+    )
+}
+
+fn balance_json(bank: &Bank) -> String {
+    let (liabilities, assets) = bank.calc_balance();
+    format!(
+        "{{\"liabilities\":{},\"assets\":{},\"net_position\":{}}}",
+        json::string(&liabilities.to_string()),
+        json::string(&assets.to_string()),
+        json::string(&liabilities.saturating_add(-assets).to_string())
+    )
+}
+
+/// Decodes `%XX` escapes in a path segment
+fn percent_decode(s: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(s.len());
+    let mut rest = s.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = tail.get(..2)?;
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+            let hex = std::str::from_utf8(hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Just enough JSON for flat request objects and for writing strings
+mod json {
+    use std::fmt::Write;
+
+    /// `s` as a JSON string literal
+    pub fn string(s: &str) -> String {
+        let mut out = String::with_capacity(s.len() + 2);
+        out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                c if (c as u32) < 0x20 => {
+                    let _ = write!(out, "\\u{:04x}", c as u32);
+                }
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+        out
+    }
+
+    /// Fields of an object whose values are strings or numbers; numbers are
+    /// kept as their source text so amounts are never rounded
+    pub fn parse_object(text: &str) -> Result<Vec<(String, String)>, String> {
+        let mut parser = Parser {
+            chars: text.chars().peekable(),
+        };
+        let mut fields = Vec::new();
+        parser.expect('{')?;
+        if parser.peek() == Some('}') {
+            parser.chars.next();
+        } else {
+            loop {
+                let key = parser.string()?;
+                parser.expect(':')?;
+                let value = match parser.peek() {
+                    Some('"') => parser.string()?,
+                    _ => parser.number()?,
+                };
+                fields.push((key, value));
+                match parser.next() {
+                    Some(',') => continue,
+                    Some('}') => break,
+                    _ => return Err("expected ',' or '}'".to_string()),
+                }
+            }
+        }
+        match parser.next() {
+            None => Ok(fields),
+            Some(_) => Err("trailing characters after object".to_string()),
+        }
+    }
+
+    struct Parser<'a> {
+        chars: std::iter::Peekable<std::str::Chars<'a>>,
+    }
+
+    impl Parser<'_> {
+        fn skip_whitespace(&mut self) {
+            while self.chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+        }
+
+        fn peek(&mut self) -> Option<char> {
+            self.skip_whitespace();
+            self.chars.peek().copied()
+        }
+
+        fn next(&mut self) -> Option<char> {
+            self.skip_whitespace();
+            self.chars.next()
+        }
+
+        fn expect(&mut self, c: char) -> Result<(), String> {
+            match self.next() {
+                Some(found) if found == c => Ok(()),
+                _ => Err(format!("expected {:?}", c)),
+            }
+        }
+
+        fn string(&mut self) -> Result<String, String> {
+            self.expect('"')?;
+            let mut out = String::new();
+            loop {
+                match self.chars.next().ok_or("unterminated string")? {
+                    '"' => return Ok(out),
+                    '\\' => match self.chars.next().ok_or("unterminated string")? {
+                        '"' => out.push('"'),
+                        '\\' => out.push('\\'),
+                        '/' => out.push('/'),
+                        'b' => out.push('\u{8}'),
+                        'f' => out.push('\u{c}'),
+                        'n' => out.push('\n'),
+                        'r' => out.push('\r'),
+                        't' => out.push('\t'),
+                        'u' => {
+                            let hex: String = self.chars.by_ref().take(4).collect();
+                            let code = u32::from_str_radix(&hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or("invalid \\u escape")?;
+                            out.push(code);
+                        }
+                        c => return Err(format!("invalid escape \\{}", c)),
+                    },
+                    c => out.push(c),
+                }
+            }
+        }
+
+        fn number(&mut self) -> Result<String, String> {
+            self.skip_whitespace();
+            let mut out = String::new();
+            while let Some(c) = self
+                .chars
+                .next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+            {
+                out.push(c);
+            }
+            if out.is_empty() {
+                return Err("expected a string or number".to_string());
+            }
+            Ok(out)
+        }
+    }
+}
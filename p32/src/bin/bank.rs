@@ -0,0 +1,391 @@
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::process;
+
+use p32::bank::{
+    Bank, LoanId, LoanTerms, MergePolicy, Money, OrderId, PaymentFrequency, SimConfig,
+    StandingOrder, TransferLimits, User,
+};
+
+const USAGE: &str = "\
+Usage: bank <file> <command> [args...]
+
+Commands:
+  create <name> <credit_bps> <debit_bps>   create a new bank file
+  add-user <name> <credit_line> <balance>  open an account
+  transfer <from> <to> <amount>            move money between users
+  accrue                                   accrue one period of interest
+  merge <other-file> [policy]              merge another bank file into this one
+  preview-merge <other-file> [policy]      show what a merge would do
+  balance                                  show liabilities and assets
+  risk [top] [--csv]                       risk report with the largest exposures
+  users                                    list users and balances
+  statement <name>                         print a user's full statement
+  trial-balance                            debit and credit totals per account
+  verify                                   check the bank's invariants
+  credit-line <name> <amount>              change a user's credit line
+  freeze <name>                            block payments out of an account
+  unfreeze <name>                          allow payments out again
+  close <name>                             close an account with zero balance
+  limits <name> <per_transfer> <daily_total> <daily_count>
+                                           set transfer limits, - for none
+  order <from> <to> <amount> <every> <start> [times]
+                                           add a standing order (days)
+  orders                                   list standing orders
+  cancel-order <id>                        cancel a standing order
+  loan <name> <principal> <rate_bps> <term> <frequency>
+                                           lend to a user over <term> installments
+  loans                                    list loans and their arrears
+  repay-loan <id> <amount>                 repay a loan early
+  simulate <seed> <steps> [--csv]          stress-test a copy of the bank
+  advance <days>                           advance the clock, running orders
+                                           and collecting loan installments
+  repl                                     read commands from standard input
+
+Merge policies are sum (default), max, ours, theirs and rename. Loan
+frequencies are weekly, monthly and quarterly.
+Amounts are in major units, e.g. 12.50. Names containing spaces can be
+quoted in the REPL.";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() < 2 {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+
+    let path = Path::new(&args[0]);
+    let result = if args[1] == "repl" {
+        repl(path)
+    } else {
+        run(path, &args[1..])
+    };
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
+
+/// Runs one command, loading and saving the bank file as needed
+fn run(path: &Path, args: &[String]) -> Result<(), Box<dyn Error>> {
+    if args[0] == "create" {
+        let [_, name, credit, debit] = args else {
+            return Err("usage: create <name> <credit_bps> <debit_bps>".into());
+        };
+        if path.exists() {
+            return Err(format!("{} already exists", path.display()).into());
+        }
+        return Ok(Bank::new(name.clone(), credit.parse()?, debit.parse()?).save(path)?);
+    }
+
+    let mut bank = Bank::load(path)?;
+    if execute(&mut bank, args)? {
+        bank.save(path)?;
+    }
+    Ok(())
+}
+
+/// Executes a command against a loaded bank; returns true if it changed state
+fn execute(bank: &mut Bank, args: &[String]) -> Result<bool, Box<dyn Error>> {
+    let strs: Vec<&str> = args.iter().map(String::as_str).collect();
+    match strs.as_slice() {
+        ["add-user", name, credit_line, balance] => {
+            let user = User::new(name.to_string(), credit_line.parse()?, balance.parse()?);
+            let id = bank.add_user(user)?;
+            println!("Added {} as account {}", name, id);
+            Ok(true)
+        }
+        ["transfer", from, to, amount] => {
+            let amount: Money = amount.parse()?;
+            bank.transfer_funds(from, to, amount)?;
+            println!("Transferred {} from {} to {}", amount, from, to);
+            Ok(true)
+        }
+        ["accrue"] => {
+            bank.accrue_interest()?;
+            println!("Interest accrued");
+            Ok(true)
+        }
+        ["merge", other, policy @ ..] => {
+            let policy = merge_policy(policy)?;
+            let report = bank.merge_bank_with(Bank::load(other)?, policy)?;
+            print!("{}", report.to_text());
+            Ok(true)
+        }
+        ["preview-merge", other, policy @ ..] => {
+            let policy = merge_policy(policy)?;
+            let report = bank.preview_merge(&Bank::load(other)?, policy)?;
+            print!("{}", report.to_text());
+            Ok(false)
+        }
+        ["balance"] => {
+            let (liabilities, assets) = bank.calc_balance();
+            println!("Liabilities: {}", liabilities);
+            println!("Assets:      {}", assets);
+            Ok(false)
+        }
+        ["risk", rest @ ..] => {
+            let csv = rest.contains(&"--csv");
+            let top = match rest.iter().find(|a| **a != "--csv") {
+                Some(top) => top.parse()?,
+                None => 5,
+            };
+            let report = bank.risk_report(top);
+            if csv {
+                print!("{}", report.to_csv());
+            } else {
+                print!("{}", report.to_text());
+            }
+            Ok(false)
+        }
+        ["users"] => {
+            for user in bank.users() {
+                println!(
+                    "{:>6}  {:<24}{:>16}{:>16}  {}",
+                    user.id().to_string(),
+                    user.name(),
+                    user.balance(),
+                    user.credit_line(),
+                    user.status()
+                );
+            }
+            Ok(false)
+        }
+        ["statement", name] => {
+            let statement = bank
+                .statement(name, 0..u64::MAX)
+                .ok_or_else(|| format!("User {} not found", name))?;
+            print!("{}", statement.to_text());
+            Ok(false)
+        }
+        ["verify"] => {
+            let violations = bank.verify();
+            for violation in &violations {
+                println!("{}", violation);
+            }
+            match violations.len() {
+                0 => println!("No violations"),
+                n => return Err(format!("{} violations found", n).into()),
+            }
+            Ok(false)
+        }
+        ["credit-line", name, amount] => {
+            bank.set_credit_line(name, amount.parse()?)?;
+            println!("Credit line of {} set to {}", name, amount);
+            Ok(true)
+        }
+        ["freeze", name] => {
+            bank.freeze(name)?;
+            println!("Froze account of {}", name);
+            Ok(true)
+        }
+        ["unfreeze", name] => {
+            bank.unfreeze(name)?;
+            println!("Unfroze account of {}", name);
+            Ok(true)
+        }
+        ["close", name] => {
+            bank.close_account(name)?;
+            println!("Closed account of {}", name);
+            Ok(true)
+        }
+        ["limits", name, per_transfer, daily_total, daily_count] => {
+            let limits = TransferLimits {
+                per_transfer: optional(per_transfer)?,
+                daily_total: optional(daily_total)?,
+                daily_count: optional(daily_count)?,
+            };
+            bank.set_transfer_limits(name, limits)?;
+            println!("Transfer limits of {} updated", name);
+            Ok(true)
+        }
+        ["trial-balance"] => {
+            print!("{}", bank.trial_balance().to_text());
+            Ok(false)
+        }
+        ["order", from, to, amount, every, start, times @ ..] => {
+            let mut order =
+                StandingOrder::new(from, to, amount.parse()?, every.parse()?, start.parse()?);
+            if let [times] = times {
+                order = order.times(times.parse()?);
+            }
+            let id = bank.add_standing_order(order)?;
+            println!("Added standing order {}", id);
+            Ok(true)
+        }
+        ["orders"] => {
+            for order in bank.standing_orders() {
+                let remaining = order.remaining.map_or("-".to_string(), |r| r.to_string());
+                println!(
+                    "{:>6}  {:<16}{:<16}{:>14}  every {} days from day {}, runs left {}",
+                    order.id().to_string(),
+                    order.from,
+                    order.to,
+                    order.amount,
+                    order.every,
+                    order.start,
+                    remaining
+                );
+            }
+            Ok(false)
+        }
+        ["cancel-order", id] => {
+            bank.cancel_standing_order(OrderId(id.trim_start_matches('#').parse()?))?;
+            println!("Cancelled standing order {}", id);
+            Ok(true)
+        }
+        ["loan", name, principal, rate, term, frequency] => {
+            let frequency = match *frequency {
+                "weekly" => PaymentFrequency::Weekly,
+                "monthly" => PaymentFrequency::Monthly,
+                "quarterly" => PaymentFrequency::Quarterly,
+                other => return Err(format!("unknown frequency: {}", other).into()),
+            };
+            let terms = LoanTerms::new(principal.parse()?, rate.parse()?, term.parse()?, frequency);
+            let id = bank.open_loan(name, terms)?;
+            let payment = bank.loan(id).map(|l| l.installments[0].payment());
+            println!(
+                "Lent {} to {} as loan {}, paying {} {}",
+                terms.principal,
+                name,
+                id,
+                payment.unwrap_or_default(),
+                frequency
+            );
+            Ok(true)
+        }
+        ["loans"] => {
+            let today = bank.today();
+            for loan in bank.loans() {
+                println!(
+                    "{:>6}  {:<24}{:>14}{:>14}  arrears {} ({} days)",
+                    loan.id().to_string(),
+                    loan.borrower,
+                    loan.terms.principal,
+                    loan.outstanding,
+                    loan.arrears(today),
+                    loan.days_past_due(today)
+                );
+            }
+            Ok(false)
+        }
+        ["repay-loan", id, amount] => {
+            let id = LoanId(id.trim_start_matches('#').parse()?);
+            let paid = bank.repay_loan(id, amount.parse()?)?;
+            println!("Repaid {} of loan {}", paid, id);
+            Ok(true)
+        }
+        ["simulate", seed, steps, rest @ ..] => {
+            let config = SimConfig::new(seed.parse()?, steps.parse()?);
+            let report = bank.clone().simulate(&config);
+            match rest {
+                [] => print!("{}", report.to_text()),
+                ["--csv"] => print!("{}", report.to_csv()),
+                _ => return Err(format!("unknown option: {}", rest.join(" ")).into()),
+            }
+            Ok(false)
+        }
+        ["advance", days] => {
+            let report = bank.advance_clock(days.parse()?);
+            for run in report.runs {
+                match run.result {
+                    Ok(()) => println!("day {}: order {} ran", run.day, run.order),
+                    Err(err) => println!("day {}: order {} failed: {}", run.day, run.order, err),
+                }
+            }
+            for failure in report.fee_failures {
+                println!(
+                    "day {}: maintenance fee for {} failed: {}",
+                    failure.day, failure.name, failure.error
+                );
+            }
+            println!("Today is day {}", bank.today());
+            Ok(true)
+        }
+        _ => Err(format!("unknown command: {}", args.join(" ")).into()),
+    }
+}
+
+fn merge_policy(args: &[&str]) -> Result<MergePolicy, Box<dyn Error>> {
+    Ok(match args {
+        [] | ["sum"] => MergePolicy::Sum,
+        ["max"] => MergePolicy::KeepMax,
+        ["ours"] => MergePolicy::KeepOurs,
+        ["theirs"] => MergePolicy::KeepTheirs,
+        ["rename"] => MergePolicy::RenameOnConflict,
+        _ => return Err(format!("unknown merge policy: {}", args.join(" ")).into()),
+    })
+}
+
+/// Parses an optional argument, where `-` means none
+fn optional<T>(arg: &str) -> Result<Option<T>, Box<dyn Error>>
+where
+    T: std::str::FromStr,
+    T::Err: Error + 'static,
+{
+    match arg {
+        "-" => Ok(None),
+        arg => Ok(Some(arg.parse()?)),
+    }
+}
+
+/// Interactive mode: one command per line, saving after every change
+fn repl(path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut bank = Bank::load(path)?;
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    loop {
+        print!("{}> ", bank.name);
+        stdout.flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        let args = split_args(&line);
+        match args.first().map(String::as_str) {
+            None => continue,
+            Some("quit" | "exit") => return Ok(()),
+            Some("help") => println!("{}", USAGE),
+            Some(_) => match execute(&mut bank, &args) {
+                Ok(true) => bank.save(path)?,
+                Ok(false) => {}
+                Err(err) => println!("error: {}", err),
+            },
+        }
+    }
+}
+
+/// Splits a REPL line on whitespace, keeping double-quoted words together
+fn split_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut started = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    args.push(std::mem::take(&mut current));
+                    started = false;
+                }
+            }
+            c => {
+                current.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        args.push(current);
+    }
+    args
+}
@@ -0,0 +1,97 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+use p32::bank::{Bank, Money};
+
+fn temp_bank(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("p32-cli-{}-{}.bank", name, std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn bank_cmd(path: &PathBuf, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_bank"))
+        .arg(path)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Runs a command that must succeed and returns its standard output
+fn bank_ok(path: &PathBuf, args: &[&str]) -> String {
+    let output = bank_cmd(path, args);
+    assert!(output.status.success(), "{:?} failed: {:?}", args, output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_cli_commands() {
+    let path = temp_bank("commands");
+    bank_ok(&path, &["create", "Ops Bank", "500", "1000"]);
+    bank_ok(&path, &["add-user", "Alice", "50", "20"]);
+    bank_ok(&path, &["add-user", "Bob", "0", "0"]);
+    bank_ok(&path, &["transfer", "Alice", "Bob", "12.50"]);
+
+    let stdout = bank_ok(&path, &["users"]);
+    assert!(stdout.contains("Alice"));
+    assert!(stdout.contains("12.50"));
+
+    let bank = Bank::load(&path).unwrap();
+    assert_eq!(bank.user("Bob").unwrap().balance(), Money::from_minor(1250));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_cli_reports_errors() {
+    let path = temp_bank("errors");
+    bank_ok(&path, &["create", "Ops Bank", "0", "0"]);
+    let output = bank_cmd(&path, &["create", "Ops Bank", "0", "0"]);
+    assert!(!output.status.success());
+
+    let output = bank_cmd(&path, &["transfer", "Alice", "Bob", "1"]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("User Alice not found"));
+    std::fs::remove_file(&path).unwrap();
+}
+
+const SCRIPT: &str = "\
+add-user \"Mary Ann\" 0 10
+add-user Bob 0 0
+transfer \"mary ann\" Bob 3
+bogus
+balance
+quit
+";
+
+#[test]
+fn test_cli_repl() {
+    let path = temp_bank("repl");
+    bank_ok(&path, &["create", "Ops Bank", "0", "0"]);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_bank"))
+        .arg(&path)
+        .arg("repl")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(SCRIPT.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("error: unknown command: bogus"));
+    assert!(stdout.contains("Liabilities: 10.00"));
+
+    let bank = Bank::load(&path).unwrap();
+    assert_eq!(
+        bank.user("Mary Ann").unwrap().balance(),
+        Money::from_minor(700)
+    );
+    std::fs::remove_file(&path).unwrap();
+}
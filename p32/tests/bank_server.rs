@@ -0,0 +1,184 @@
+#![cfg(feature = "server")]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+
+use p32::bank::{Bank, Money, TransferLimits, User};
+
+/// A running `bank-server`, killed when dropped
+struct Server {
+    child: Child,
+    addr: String,
+    path: PathBuf,
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Starts a server on an ephemeral port over a bank with Alice, Bob limited
+/// to 5.00 per transfer, a frozen Carol and a closed account for Eve
+fn start(name: &str) -> Server {
+    let path =
+        std::env::temp_dir().join(format!("p32-server-{}-{}.bank", name, std::process::id()));
+    let mut bank = Bank::new("Web Bank".to_string(), 0, 1000);
+    bank.add_user(User::new("Alice".to_string(), 0.into(), 5000.into()))
+        .unwrap();
+    bank.add_user(User::new("Bob".to_string(), 1000.into(), 0.into()))
+        .unwrap();
+    bank.add_user(User::new("Carol Ann".to_string(), 0.into(), 100.into()))
+        .unwrap();
+    bank.freeze("Carol Ann").unwrap();
+    bank.add_user(User::new("Eve".to_string(), 0.into(), 0.into()))
+        .unwrap();
+    bank.close_account("Eve").unwrap();
+    let limits = TransferLimits {
+        per_transfer: Some(500.into()),
+        ..TransferLimits::default()
+    };
+    bank.set_transfer_limits("Bob", limits).unwrap();
+    bank.save(&path).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_bank-server"))
+        .arg(&path)
+        .arg("127.0.0.1:0")
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let addr = line
+        .trim()
+        .strip_prefix("Listening on http://")
+        .unwrap()
+        .to_string();
+    Server { child, addr, path }
+}
+
+/// Sends one request and returns the status code and body
+fn request(server: &Server, method: &str, path: &str, body: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(&server.addr).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+    (status, body.to_string())
+}
+
+#[test]
+fn test_read_endpoints() {
+    let server = start("read");
+    let (status, body) = request(&server, "GET", "/users", "");
+    assert_eq!(status, 200);
+    assert!(body.starts_with("{\"users\":[{\"id\":1,\"name\":\"Alice\""));
+    assert!(body.contains("\"status\":\"frozen\""));
+
+    let (status, body) = request(&server, "GET", "/users/carol%20ann", "");
+    assert_eq!(status, 200);
+    assert!(body.contains("\"name\":\"Carol Ann\",\"balance\":\"1.00\""));
+
+    let (status, body) = request(&server, "GET", "/balance", "");
+    assert_eq!(status, 200);
+    assert_eq!(
+        body,
+        "{\"liabilities\":\"51.00\",\"assets\":\"0.00\",\"net_position\":\"51.00\"}"
+    );
+
+    assert_eq!(request(&server, "GET", "/nowhere", "").0, 404);
+    assert_eq!(request(&server, "DELETE", "/users", "").0, 405);
+    // Only two hex digits make an escape, so "%+1" is not byte 0x01
+    assert_eq!(request(&server, "GET", "/users/%+1", "").0, 400);
+}
+
+#[test]
+fn test_oversized_head_is_rejected() {
+    let server = start("head");
+    // Exactly the server's 8 KiB head limit, with no end of headers in sight
+    let mut head = "GET /users HTTP/1.1\r\nX-Filler: ".to_string();
+    head.push_str(&"a".repeat(8 * 1024 - head.len()));
+
+    let mut stream = TcpStream::connect(&server.addr).unwrap();
+    stream.write_all(head.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 431 "), "{}", response);
+
+    // The server carries on with the next client
+    assert_eq!(request(&server, "GET", "/balance", "").0, 200);
+}
+
+#[test]
+fn test_transfer_and_interest_are_saved() {
+    let server = start("write");
+    let body = r#"{"from": "alice", "to": "Bob", "amount": "12.50"}"#;
+    let (status, body) = request(&server, "POST", "/transfers", body);
+    assert_eq!(status, 200);
+    assert_eq!(body, r#"{"from":"alice","to":"Bob","amount":"12.50"}"#);
+
+    // Numbers are accepted too and never go through floating point
+    let body = r#"{"from":"Alice","to":"Bob","amount":0.1}"#;
+    assert_eq!(request(&server, "POST", "/transfers", body).0, 200);
+
+    let (status, body) = request(&server, "POST", "/interest", "");
+    assert_eq!(status, 200);
+    assert!(body.contains("\"liabilities\":\"56.10\""));
+
+    let bank = Bank::load(&server.path).unwrap();
+    assert_eq!(bank.user("Bob").unwrap().balance(), Money::from_minor(1386));
+    assert_eq!(
+        bank.user("Alice").unwrap().balance(),
+        Money::from_minor(4114)
+    );
+}
+
+#[test]
+fn test_transfer_errors_map_to_status_codes() {
+    let server = start("errors");
+    let cases = [
+        ("Alice", "Dave", "1", 404, "user-not-found"),
+        ("Alice", "Bob", "50.01", 422, "insufficient-funds"),
+        ("Bob", "Alice", "10.01", 422, "credit-limit-exceeded"),
+        ("Alice", "Bob", "0", 400, "zero-amount"),
+        ("Alice", "Bob", "-1", 400, "negative-amount"),
+        ("Alice", "alice", "1", 400, "self-transfer"),
+        ("Carol Ann", "Bob", "1", 423, "account-frozen"),
+        ("Alice", "Eve", "1", 410, "account-closed"),
+        ("Bob", "Alice", "5.01", 422, "limit-exceeded"),
+    ];
+    for (from, to, amount, code, kind) in cases {
+        let body = format!(
+            r#"{{"from":"{}","to":"{}","amount":"{}"}}"#,
+            from, to, amount
+        );
+        let (status, body) = request(&server, "POST", "/transfers", &body);
+        assert_eq!(status, code, "{}", body);
+        assert!(
+            body.starts_with(&format!("{{\"error\":\"{}\"", kind)),
+            "{}",
+            body
+        );
+        assert!(body.contains("\"message\":"));
+    }
+
+    let (status, body) = request(&server, "POST", "/transfers", r#"{"from":"Alice"}"#);
+    assert_eq!(status, 400);
+    assert!(body.contains("missing field"));
+    assert_eq!(request(&server, "POST", "/transfers", "not json").0, 400);
+}